@@ -4,7 +4,7 @@ pub fn clean_cli_args() -> impl Iterator<Item = String> {
     //! Gets the list of args passed to util from std::env.
     //! Args of the form '--arg' or 'arg' are unchanged.
     //! Args of the form '-abc' are changed into '-a -b -c'.
-    return clean_args(std::env::args().into_iter().skip(1));
+    return clean_args(std::env::args().skip(1));
 }
 pub fn clear_terminal() {
     print!("{}[2J", 27 as char);
@@ -16,7 +16,7 @@ fn clean_args<I>(args: I) -> impl Iterator<Item = String> where I: Iterator<Item
             vec![x]
         }
         else {
-            x.chars().into_iter().skip(1).map(|x| format!("-{}", x)).collect()
+            x.chars().skip(1).map(|x| format!("-{}", x)).collect()
         }
     });
 }
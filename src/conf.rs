@@ -1,17 +1,165 @@
 use toml::{Table, Value};
-use std::path::PathBuf;
-use serde_derive::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use serde_derive::Deserialize;
 use crate::{self as mythos_core, printwarn};
-use crate::{dirs, printerror};
+use crate::{dirs, logger, printerror};
 
-const VALID_CONFIG_EXT: [&str; 3] = [
+mod coerce;
+mod diff;
+mod dir;
+mod edit;
+mod env;
+mod error;
+mod format;
+mod include;
+mod interpolate;
+mod lazy;
+mod merge;
+mod migrate;
+mod origin;
+mod path;
+mod profile;
+mod schema;
+mod units;
+mod value;
+mod view;
+mod watch;
+pub use coerce::Coercion;
+pub use diff::ConfigDiff;
+pub use error::ConfigError;
+pub use merge::ArrayMerge;
+pub use migrate::{Migration, Migrations, Step};
+pub use origin::Origin;
+pub use path::join as join_keys;
+pub use schema::{ConfigSchema, KeySpec, SchemaViolation, ValueKind, Violation};
+pub use units::Color;
+pub use value::{ConfigTable, ConfigValue};
+pub use view::ConfigRef;
+pub use watch::{watch, watch_channel, watch_with, ConfigWatcher, WatchResult};
+
+const VALID_CONFIG_EXT: &[&str] = &[
     "conf",
     "ini",
     "toml",
+    #[cfg(feature = "json")]
+    "json",
+    #[cfg(feature = "yaml")]
+    "yaml",
+    #[cfg(feature = "yaml")]
+    "yml",
 ];
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MythosConfig(Table);
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct MythosConfig {
+    table: Table,
+    /// File or directory the config was read from. None if it was built from multiple sources.
+    #[serde(skip)]
+    source: Option<PathBuf>,
+    /// Normalized path of every leaf -> where its value came from.
+    #[serde(skip)]
+    origins: origin::Origins,
+    /// Directory config files that have not been read yet, see ConfigOptions::lazy_dirs.
+    #[serde(skip)]
+    lazy: lazy::LazyFiles,
+    /// Name of the profile that was applied.
+    #[serde(skip)]
+    profile: Option<String>,
+    /// How getters treat values of the wrong type.
+    #[serde(skip)]
+    coercion: Coercion,
+    /// Whether datetime getters parse strings, see ConfigOptions::parse_datetime_strings.
+    #[serde(skip)]
+    parse_datetime_strings: bool,
+    /// Where set_*(...) edits are recorded and saved.
+    #[serde(skip)]
+    editor: edit::Editor,
+}
+
+/// Controls how configs are loaded.
+/// Fields may be added over time, so construct with `..Default::default()`.
+#[derive(Debug, Clone)]
+pub struct ConfigOptions {
+    /// How arrays are combined when a later layer sets the same key.
+    pub array_merge: ArrayMerge,
+    /// Paths merged on top of Config and LocalConfig, in order.
+    /// As with util names, the file extension can be omitted.
+    pub layers: Vec<PathBuf>,
+    /// Apply MYTHOS_<UTIL>__<SECTION>__<KEY> environment variables on top of the file values.
+    pub env_overrides: bool,
+    /// Expand ${ENV}, ${mythos:DIR} and ${self:key} inside string values, and $$ to $.
+    /// Off by default, so existing configs with a literal "${name}" or "$$" are read unchanged.
+    /// When on, a reference that can't be expanded fails the load with ConfigError::Interpolation.
+    pub interpolate: bool,
+    /// In directory configs, read subdirectories as arrays of their entries instead of tables.
+    pub dir_arrays: bool,
+    /// In directory configs, files with these extensions are read as strings instead of being parsed, e.g. ["sh", "tmpl"].
+    /// Files that are not valid UTF-8 are read as arrays of bytes.
+    pub raw_extensions: Vec<String>,
+    /// Same as raw_extensions, but for every file below these directories. Paths are relative to the config directory.
+    pub raw_dirs: Vec<PathBuf>,
+    /// In directory configs, only read files the first time one of their keys is accessed.
+    /// Modifying the config, merging it or applying env_overrides reads every file.
+    /// ${self:...} references in lazily read files can only point to keys in the same file.
+    pub lazy_dirs: bool,
+    /// TOML config used as the lowest layer by load_layered_with(...), usually the util's include_str!(...) default.
+    /// With a default, load_layered_with(...) succeeds even if the util has no config files.
+    pub default_config: Option<String>,
+    /// How getters treat values of the wrong type, e.g. whether get_float(...) accepts 1. Strict by default.
+    pub coercion: Coercion,
+    /// Let get_datetime(...), get_date(...) and the other datetime getters parse strings, e.g. "1979-05-27T07:32:00Z",
+    /// without the rest of Coercion::Lenient. Strings use TOML's datetime syntax, which accepts RFC 3339.
+    pub parse_datetime_strings: bool,
+    /// Profile to apply, see profile(). Takes precedence over $MYTHOS_<UTIL>_PROFILE and $MYTHOS_PROFILE.
+    pub profile: Option<String>,
+}
+
+impl Default for ConfigOptions {
+    fn default() -> ConfigOptions {
+        return ConfigOptions {
+            array_merge: ArrayMerge::default(),
+            layers: Vec::new(),
+            env_overrides: false,
+            interpolate: false,
+            dir_arrays: false,
+            raw_extensions: Vec::new(),
+            raw_dirs: Vec::new(),
+            lazy_dirs: false,
+            default_config: None,
+            coercion: Coercion::Strict,
+            parse_datetime_strings: false,
+            profile: None,
+        };
+    }
+}
+
+impl serde::Serialize for MythosConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        return self.full_table().serialize(serializer);
+    }
+}
+
+impl IntoIterator for MythosConfig {
+    type Item = (String, ConfigValue);
+    type IntoIter = std::vec::IntoIter<(String, ConfigValue)>;
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.load_lazy();
+        return self.table.iter()
+            .map(|(key, val)| (key.to_owned(), value::from_toml(val)))
+            .collect::<Vec<_>>()
+            .into_iter();
+    }
+}
+
+impl IntoIterator for &MythosConfig {
+    type Item = (String, ConfigValue);
+    type IntoIter = std::vec::IntoIter<(String, ConfigValue)>;
+    fn into_iter(self) -> Self::IntoIter {
+        return self.view().into_iter();
+    }
+}
 
 impl MythosConfig {
     /**
@@ -21,240 +169,864 @@ impl MythosConfig {
      *
      * This will always try to open a file, unlike open(...), which will treat directories as
      * abstract config files.
+     * Errors are printed, see load_file(...) to handle them instead.
      */
     pub fn open_file(path_snippet: &str) -> Option<MythosConfig> {
-        let path = match try_get_file(path_snippet, false) {
-            Some(path) => path,
-            None => {
-                printerror!("Could not find a config file for '{}'", path_snippet);
-                return None;
-            }
-        };
-        return MythosConfig::read_file(&path);
+        return report(MythosConfig::load_file(path_snippet));
+    }
+    /// Same as open_file(...), but returns the reason the config could not be loaded.
+    pub fn load_file(path_snippet: &str) -> Result<MythosConfig, ConfigError> {
+        return MythosConfig::load_file_with(path_snippet, &ConfigOptions::default());
+    }
+    pub fn open_file_with(path_snippet: &str, options: &ConfigOptions) -> Option<MythosConfig> {
+        return report(MythosConfig::load_file_with(path_snippet, options));
+    }
+    pub fn load_file_with(path_snippet: &str, options: &ConfigOptions) -> Result<MythosConfig, ConfigError> {
+        let path = try_get_file(path_snippet, false)?;
+        let mut config = MythosConfig::read_file(&path)?;
+        config.editor.util_name = Some(path_snippet.to_string());
+        config.apply_options(path_snippet, options)?;
+        return Ok(config);
+    }
+    /**
+     * Parse a TOML config that is not read from a file, e.g. a default embedded with include_str!(...).
+     * Includes are not followed and options are not applied.
+     * Parse errors point to "<embedded>".
+     */
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(contents: &str) -> Result<MythosConfig, ConfigError> {
+        let (table, format) = format::parse(Path::new("<embedded>"), contents)?;
+        let lines = format::lines(contents, format);
+        let mut config = MythosConfig::from_table(table);
+        config.origins = origin::leaves(&config.table).into_iter()
+            .map(|key| {
+                let line = lines.get(&key).copied();
+                (key, Origin::Embedded { line })
+            })
+            .collect();
+        return Ok(config);
     }
-    fn read_file(path: &PathBuf) -> Option<MythosConfig> {
-        let contents = match std::fs::read_to_string(&path) {
+    /// Read a config from path, instead of searching the mythos config dirs. The extension can be omitted.
+    pub fn open_path(path: &Path) -> Option<MythosConfig> {
+        return MythosConfig::open_path_with(path, &ConfigOptions::default());
+    }
+    pub fn open_path_with(path: &Path, options: &ConfigOptions) -> Option<MythosConfig> {
+        return report(MythosConfig::load_path_with(path, options));
+    }
+    pub fn load_path(path: &Path) -> Result<MythosConfig, ConfigError> {
+        return MythosConfig::load_path_with(path, &ConfigOptions::default());
+    }
+    /**
+     * Like load_file_with, but reads path instead of searching the mythos config dirs.
+     * The file stem stands in for the util name when selecting a profile or env overrides.
+     */
+    pub fn load_path_with(path: &Path, options: &ConfigOptions) -> Result<MythosConfig, ConfigError> {
+        let path = clean_and_validate(path.to_path_buf(), true)?;
+        let util_name = path.file_stem().and_then(|x| x.to_str()).unwrap_or_default().to_string();
+        let mut config = MythosConfig::read_path(&path, options)?;
+        config.apply_options(&util_name, options)?;
+        return Ok(config);
+    }
+    /**
+     * Install default_toml as util_name's config on first run.
+     * If neither $MYTHOS_LOCAL_CONFIG_DIR nor $MYTHOS_CONFIG_DIR has a config for util_name,
+     * default_toml is written to $MYTHOS_LOCAL_CONFIG_DIR/util_name.toml.
+     * Returns the path that was written to, or None if a config already exists.
+     */
+    pub fn ensure_default(util_name: &str, default_toml: &str) -> Result<Option<PathBuf>, ConfigError> {
+        match try_get_file(util_name, true) {
+            Ok(path) => {
+                logger::info(&format!("Config for {util_name} found at {}, default was not installed.", path.display()));
+                return Ok(None);
+            },
+            Err(ConfigError::NotFound { .. }) => (),
+            Err(err) => return Err(err)
+        }
+        // Don't install a default that could never be read.
+        MythosConfig::from_str(default_toml)?;
+
+        let parent = Path::new(util_name).parent().and_then(|x| x.to_str()).unwrap_or_default();
+        dirs::make_dir(dirs::MythosDir::LocalConfig, parent)
+            .map_err(|err| ConfigError::io(&dirs::expand_path(dirs::MythosDir::LocalConfig, parent), err))?;
+        let path = dirs::expand_path(dirs::MythosDir::LocalConfig, util_name).with_extension("toml");
+        std::fs::write(&path, default_toml).map_err(|err| ConfigError::io(&path, err))?;
+        logger::info(&format!("Installed default config for {util_name} at {}.", path.display()));
+        return Ok(Some(path));
+    }
+    /**
+     * Write default_toml to defaults_path(util_name), so tools can compare a user's config with the util's defaults,
+     * e.g. `mythos-conf diff util --against defaults`. Call it with the same default as ensure_default(...).
+     * The file is only rewritten when default_toml changed. Returns the path.
+     */
+    pub fn install_defaults(util_name: &str, default_toml: &str) -> Result<PathBuf, ConfigError> {
+        MythosConfig::from_str(default_toml)?;
+        let path = defaults_path(util_name);
+        if std::fs::read_to_string(&path).is_ok_and(|x| x == default_toml) {
+            return Ok(path);
+        }
+        dirs::make_dir(dirs::MythosDir::Data, util_name)
+            .map_err(|err| ConfigError::io(&dirs::expand_path(dirs::MythosDir::Data, util_name), err))?;
+        std::fs::write(&path, default_toml).map_err(|err| ConfigError::io(&path, err))?;
+        return Ok(path);
+    }
+    fn read_file(path: &Path) -> Result<MythosConfig, ConfigError> {
+        return MythosConfig::read_included_file(path, &mut Vec::new());
+    }
+    fn read_included_file(path: &Path, chain: &mut Vec<PathBuf>) -> Result<MythosConfig, ConfigError> {
+        //! chain is the list of files that included path, used to detect cycles.
+        let contents = match std::fs::read_to_string(path) {
             Ok(contents) => contents,
-            Err(err) => {
-                printerror!("Could not read config file for {:?}. Error msg: {}", path, err.to_string());
-                return None;
-            }
+            Err(err) => return Err(ConfigError::io(path, err))
         };
 
-        return match toml::from_str(&contents) {
-            Ok(config) => Some(MythosConfig(config)),
-            Err(_) => None
-        };
+        let (mut table, format) = format::parse(path, &contents)?;
+        let raw = table.clone();
+        let includes = include::take_includes(&mut table, path)?;
+        let lines = format::lines(&contents, format);
+        let mut config = MythosConfig::from_table(table);
+        config.origins = origin::from_file(path, &config.table, &lines);
+        if !includes.is_empty() {
+            chain.push(path.canonicalize().unwrap_or(path.to_owned()));
+            let mut base = MythosConfig::default();
+            for include in includes {
+                let canonical = include.canonicalize().unwrap_or(include.to_owned());
+                if chain.contains(&canonical) {
+                    let mut cycle = chain.clone();
+                    cycle.push(canonical);
+                    return Err(ConfigError::IncludeCycle(cycle));
+                }
+                let included = match MythosConfig::read_included_file(&include, chain) {
+                    Ok(included) => included,
+                    Err(err @ (ConfigError::Include { .. } | ConfigError::IncludeCycle(_))) => return Err(err),
+                    Err(err) => {
+                        let mut chain = chain.clone();
+                        chain.push(canonical);
+                        return Err(ConfigError::Include { chain, source: Box::new(err) });
+                    }
+                };
+                base.merge(included, ArrayMerge::Replace);
+            }
+            chain.pop();
+            base.merge(config, ArrayMerge::Replace);
+            config = base;
+        }
+
+        config.source = Some(path.to_owned());
+        // Edits to other formats are saved as TOML, so the original file can't be used as a base.
+        let seed = if format == format::ConfigFormat::Toml { edit::Seed::File(path.to_owned()) } else { edit::Seed::Table(raw) };
+        config.editor = edit::Editor::new(None, seed);
+        return Ok(config);
     }
     /**
      * Tries to open config file.
      * If path is a dir, it is treated as an abstract config file, where each file and subdirectory are entries.
+     * Hidden entries are skipped. Subdirectories are tables, or arrays with ConfigOptions::dir_arrays.
+     * Files that are not configs, e.g. scripts, can be read as strings with ConfigOptions::raw_extensions.
      * If path is a file, this method acts like MythosConfig::open_file(...).
+     * Errors are printed, see load(...) to handle them instead.
      */
     pub fn open(path_snippet: &str) -> Option<MythosConfig> {
+        return report(MythosConfig::load(path_snippet));
+    }
+    /// Same as open(...), but returns the reason the config could not be loaded.
+    pub fn load(path_snippet: &str) -> Result<MythosConfig, ConfigError> {
+        return MythosConfig::load_with(path_snippet, &ConfigOptions::default());
+    }
+    pub fn open_with(path_snippet: &str, options: &ConfigOptions) -> Option<MythosConfig> {
+        return report(MythosConfig::load_with(path_snippet, options));
+    }
+    pub fn load_with(path_snippet: &str, options: &ConfigOptions) -> Result<MythosConfig, ConfigError> {
         let path = try_get_file(path_snippet, true)?;
+        let mut config = MythosConfig::read_path(&path, options)?;
+        // Abstract configs have no single file that save() could write to.
+        if path.is_file() {
+            config.editor.util_name = Some(path_snippet.to_string());
+        }
+        config.apply_options(path_snippet, options)?;
+        return Ok(config);
+    }
+    /**
+     * Opens config using open(...) and converts it into T.
+     * e.g. 
+     * #[derive(Deserialize)]
+     * struct Settings { #[serde(default)] width: i64 }
+     * let settings: Settings = MythosConfig::open_as("util_name")?;
+     */
+    pub fn open_as<T>(path_snippet: &str) -> Result<T, ConfigError> where T: serde::de::DeserializeOwned {
+        return MythosConfig::load(path_snippet)?.deserialize();
+    }
+    pub fn deserialize<T>(&self) -> Result<T, ConfigError> where T: serde::de::DeserializeOwned {
+        //! Convert the entire config into T.
+        //! If a value cannot be converted, the error contains its key.
+        return serde_path_to_error::deserialize(Value::Table(self.full_table())).map_err(|err| {
+            let key = err.path().to_string();
+            ConfigError::Deserialize {
+                key: if key == "." { String::new() } else { key },
+                message: err.into_inner().message().to_string(),
+            }
+        });
+    }
+    /**
+     * Opens every layer of a util's config and deep-merges them.
+     * Layers are applied in this order, with later layers taking precedence:
+     * - options.default_config, if set
+     * - $MYTHOS_CONFIG_DIR/util_name
+     * - $MYTHOS_LOCAL_CONFIG_DIR/util_name
+     * - Any extra layers in options.layers
+     *
+     * Missing layers are skipped. If every layer is missing and there is no default_config, NotFound is returned.
+     * Errors are printed, see load_layered(...) to handle them instead.
+     */
+    pub fn open_layered(util_name: &str) -> Option<MythosConfig> {
+        return report(MythosConfig::load_layered(util_name));
+    }
+    /// Same as open_layered(...), but returns the reason the config could not be loaded.
+    pub fn load_layered(util_name: &str) -> Result<MythosConfig, ConfigError> {
+        return MythosConfig::load_layered_with(util_name, &ConfigOptions::default());
+    }
+    pub fn load_layered_with(util_name: &str, options: &ConfigOptions) -> Result<MythosConfig, ConfigError> {
+        let mut paths = vec![
+            dirs::expand_path(dirs::MythosDir::Config, util_name),
+            dirs::expand_path(dirs::MythosDir::LocalConfig, util_name),
+        ];
+        paths.extend(options.layers.iter().cloned());
+
+        let mut config = options.default_config.as_deref().map(MythosConfig::from_str).transpose()?;
+        let mut tried: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            let layer = match clean_and_validate(path, true) {
+                Ok(path) => MythosConfig::read_path(&path, options)?,
+                Err(ConfigError::NotFound { tried: layer_tried, .. }) => {
+                    tried.extend(layer_tried);
+                    continue;
+                },
+                Err(err) => return Err(err)
+            };
+            match config.as_mut() {
+                Some(config) => config.merge(layer, options.array_merge),
+                None => config = Some(layer)
+            }
+        }
+        let mut config = config.ok_or(ConfigError::NotFound { path_snippet: util_name.to_string(), tried })?;
+
+        // Edits only belong in the local layer, the other layers are still read underneath it.
+        config.editor = match clean_and_validate(dirs::expand_path(dirs::MythosDir::LocalConfig, util_name), true) {
+            Ok(path) if path.is_file() && format::is_toml(&path) => edit::Editor::new(Some(util_name), edit::Seed::File(path)),
+            // Edits are saved as TOML, a local file in another format can't be used as a base.
+            Ok(path) if path.is_file() => edit::Editor::new(Some(util_name), edit::Seed::Empty),
+            Ok(_) => edit::Editor::default(),
+            Err(_) => edit::Editor::new(Some(util_name), edit::Seed::Empty),
+        };
+        config.apply_options(util_name, options)?;
+        return Ok(config);
+    }
+    fn apply_options(&mut self, util_name: &str, options: &ConfigOptions) -> Result<(), ConfigError> {
+        //! Apply the options that take effect after every file has been read.
+        self.freeze_seed();
+        self.coercion = options.coercion;
+        self.parse_datetime_strings = options.parse_datetime_strings;
+        let defined = self.view().get_subsection(profile::SECTION).map(|x| x.list_keys()).unwrap_or_default();
+        if let Some(name) = profile::select(&defined, util_name, options.profile.as_deref()) {
+            self.apply_profile(&name, options.array_merge)?;
+        }
+        if options.env_overrides {
+            self.load_lazy();
+            let sources = env::apply_overrides(&mut self.table, util_name);
+            self.origins.extend(sources.into_iter().map(|(key, var)| (key, Origin::Env(var))));
+            origin::prune(&mut self.origins, &self.table);
+        }
+        // Last, so that references see the overridden values.
+        if options.interpolate {
+            interpolate::interpolate(&mut self.table, &origin::raw_keys(&self.origins))?;
+        }
+        return Ok(());
+    }
+    fn apply_profile(&mut self, name: &str, array_merge: ArrayMerge) -> Result<(), ConfigError> {
+        //! Merge profile name, and the profiles it inherits from, over the base table.
+        self.load_lazy();
+        for (name, overlay) in profile::resolve(&self.table, name)? {
+            let prefix = format!("{}.", path::join(&[profile::SECTION, &name]));
+            let origins: Vec<(String, Origin)> = self.origins.iter()
+                .filter_map(|(path, origin)| Some((path.strip_prefix(&prefix)?.to_string(), origin.to_owned())))
+                .collect();
+            self.origins.extend(origins);
+            merge::merge_tables(&mut self.table, overlay, array_merge);
+        }
+        origin::prune(&mut self.origins, &self.table);
+        self.profile = Some(name.to_string());
+        return Ok(());
+    }
+    fn read_path(path: &Path, options: &ConfigOptions) -> Result<MythosConfig, ConfigError> {
         if path.is_file() { 
-            return MythosConfig::read_file(&path) 
+            return MythosConfig::read_file(path);
+        };
+        return dir::DirReader::read(path, options);
+    }
+    pub fn extend(&mut self, key: &str, other: MythosConfig) {
+        let prefix = path::join(&[key]);
+        for (path, origin) in other.origins {
+            self.origins.insert(format!("{prefix}.{path}"), origin);
+        }
+        for (path, file) in other.lazy {
+            self.lazy.insert(format!("{prefix}.{path}"), file);
+        }
+        self.table.insert(key.to_string(), toml::Value::Table(other.table));
+    }
+    pub fn merge(&mut self, mut other: MythosConfig, array_merge: ArrayMerge) {
+        //! Deep-merge other into self. Values in other take precedence.
+        self.load_lazy();
+        other.load_lazy();
+        if self.source != other.source {
+            self.source = None;
+        }
+        self.origins.extend(other.origins);
+        merge::merge_tables(&mut self.table, other.table, array_merge);
+        origin::prune(&mut self.origins, &self.table);
+    }
+    pub fn set_coercion(&mut self, coercion: Coercion) {
+        //! Change how getters treat values of the wrong type, see Coercion.
+        self.coercion = coercion;
+    }
+    pub fn coercion(&self) -> Coercion {
+        return self.coercion;
+    }
+    pub fn set_parse_datetime_strings(&mut self, parse: bool) {
+        //! See ConfigOptions::parse_datetime_strings.
+        self.parse_datetime_strings = parse;
+    }
+    pub fn profile(&self) -> Option<&str> {
+        //! Name of the profile that was applied, if any.
+        //! The [profile.*] tables stay in the config, so every profile can still be read.
+        return self.profile.as_deref();
+    }
+    pub fn env_source(&self, key: &str) -> Option<&str> {
+        //! If key was set by an environment variable, returns the variable's name.
+        return match self.origin(key) {
+            Some(Origin::Env(var)) => Some(var),
+            _ => None
+        };
+    }
+    pub fn origin(&self, key: &str) -> Option<&Origin> {
+        //! Where the value at key came from: a file and line, an environment variable, a default, or set_*(...).
+        //! Array elements share the array's origin. Returns None for tables and missing keys.
+        return self.view().origin(key);
+    }
+    fn from_table(table: Table) -> MythosConfig {
+        return MythosConfig { table, ..Default::default() };
+    }
+    fn subsection(&self, key: &str, table: Table) -> MythosConfig {
+        //! Wrap table, which was found at key, keeping any metadata that belongs to it.
+        //! key is empty for the root.
+        let key = path::normalize(key);
+        let prefix = if key.is_empty() { key.to_owned() } else { format!("{key}.") };
+        let origins = self.origins.iter()
+            .filter_map(|(path, origin)| Some((path.strip_prefix(&prefix)?.to_string(), origin.to_owned())))
+            .collect();
+        let lazy = lazy::rebase(&self.lazy, &key);
+        return MythosConfig { table, origins, lazy, source: self.source.clone(),
+            coercion: self.coercion, parse_datetime_strings: self.parse_datetime_strings, ..Default::default()
+        };
+    }
+    fn load_lazy(&mut self) {
+        //! Read every lazy file into the table.
+        for (key, file) in std::mem::take(&mut self.lazy) {
+            let (loaded, segments) = match (file.into_loaded(), path::parse(&key)) {
+                (Some(loaded), Some(segments)) => (loaded, segments),
+                _ => continue
+            };
+            for (path, origin) in loaded.origins {
+                self.origins.insert(format!("{key}.{path}"), origin);
+            }
+            if let edit::Seed::Table(seed) = &mut self.editor.seed {
+                path::insert(seed, &segments, loaded.raw);
+            }
+            path::insert(&mut self.table, &segments, loaded.value);
+        }
+    }
+    fn freeze_seed(&mut self) {
+        //! Call before adding values that save() should not write, e.g. defaults, overrides and expanded references.
+        if let edit::Seed::Current = self.editor.seed {
+            self.editor.seed = edit::Seed::Table(self.table.clone());
+        }
+    }
+    fn full_table(&self) -> Table {
+        //! Copy of the table, including lazy files.
+        let mut table = self.table.clone();
+        lazy::fill(&mut table, &self.lazy, "");
+        return table;
+    }
+
+    pub fn validate(&self, schema: &ConfigSchema) -> Result<(), Vec<SchemaViolation>> {
+        //! Check every key in schema. All violations are returned, not just the first.
+        let violations: Vec<SchemaViolation> = schema.keys.iter().filter_map(|spec| {
+            let val = self.get(&spec.key);
+            Some(SchemaViolation {
+                violation: spec.check(val)?,
+                key: spec.key.to_owned(),
+                file: match self.origin(&spec.key) {
+                    Some(Origin::File { path, .. }) => Some(path.to_owned()),
+                    _ => self.source.clone()
+                },
+                value: val.map(value::from_toml),
+            })
+        }).collect();
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+        return Err(violations);
+    }
+    pub fn apply_defaults(&mut self, schema: &ConfigSchema) {
+        //! Set every missing key that has a default. 
+        //! Defaults are not saved by save().
+        self.load_lazy();
+        self.freeze_seed();
+        for spec in &schema.keys {
+            let default = match &spec.default {
+                Some(default) if self.get(&spec.key).is_none() => value::to_toml(default.to_owned()),
+                _ => continue
+            };
+            let origins = origin::leaves_of(&spec.key, &default);
+            match path::parse(&spec.key) {
+                Some(segments) if path::insert(&mut self.table, &segments, default) => {
+                    self.origins.extend(origins.into_iter().map(|key| (key, Origin::Default)));
+                },
+                _ => printwarn!("Could not apply default for '{}'. One of its parents is not a table.", spec.key)
+            }
+        }
+    }
+    pub fn set_string(&mut self, key: &str, val: &str) -> Result<(), ConfigError> {
+        //! Set key, creating any missing sections. 
+        //! Changes are kept in memory until save() or save_to(...) is called.
+        return self.set(key, Value::String(val.to_string()));
+    }
+    pub fn set_integer(&mut self, key: &str, val: i64) -> Result<(), ConfigError> {
+        return self.set(key, Value::Integer(val));
+    }
+    pub fn set_float(&mut self, key: &str, val: f64) -> Result<(), ConfigError> {
+        return self.set(key, Value::Float(val));
+    }
+    pub fn set_boolean(&mut self, key: &str, val: bool) -> Result<(), ConfigError> {
+        return self.set(key, Value::Boolean(val));
+    }
+    pub fn set_array(&mut self, key: &str, val: Vec<ConfigValue>) -> Result<(), ConfigError> {
+        return self.set(key, value::to_toml(ConfigValue::Array(val)));
+    }
+    fn set(&mut self, key: &str, val: Value) -> Result<(), ConfigError> {
+        let segments = path::parse(key).ok_or(ConfigError::InvalidKey(key.to_string()))?;
+        self.load_lazy();
+        // Check the table first, the document can't be rolled back. They differ when e.g. a parent key comes from an include.
+        let mut table = self.table.clone();
+        if !path::insert(&mut table, &segments, val.clone()) {
+            return Err(ConfigError::InvalidKey(key.to_string()));
+        }
+        let doc = self.editor.document(&self.table)?;
+        if !edit::set(doc, &segments, &val) {
+            return Err(ConfigError::InvalidKey(key.to_string()));
+        }
+        self.table = table;
+        origin::prune(&mut self.origins, &self.table);
+        self.origins.insert(path::normalize(key), Origin::Set);
+        return Ok(());
+    }
+    pub fn remove(&mut self, key: &str) -> Result<bool, ConfigError> {
+        //! Remove key. Returns false if key did not exist.
+        //! Only the file that save() writes is edited. A key from another layer or an include is removed from
+        //! this config, but returns when the config is read again. Override it with a setter instead.
+        let segments = path::parse(key).ok_or(ConfigError::InvalidKey(key.to_string()))?;
+        self.load_lazy();
+        let doc = self.editor.document(&self.table)?;
+        edit::remove(doc, &segments);
+        let removed = path::remove(&mut self.table, &segments).is_some();
+        origin::prune(&mut self.origins, &self.table);
+        return Ok(removed);
+    }
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), ConfigError> {
+        //! Move the value at from to to, keeping its comments and origins. Does nothing if from does not exist.
+        let from_segments = path::parse(from).ok_or(ConfigError::InvalidKey(from.to_string()))?;
+        let to_segments = path::parse(to).ok_or(ConfigError::InvalidKey(to.to_string()))?;
+        self.load_lazy();
+        if path::lookup(&self.table, from).is_none() {
+            return Ok(());
+        }
+        if path::lookup(&self.table, to).is_some() {
+            return Err(ConfigError::Migration(format!("cannot move '{from}' to '{to}', which already exists")));
+        }
+        let doc = self.editor.document(&self.table)?;
+        let val = path::remove(&mut self.table, &from_segments).unwrap_or(Value::Table(Table::new()));
+        let moved = match edit::remove(doc, &from_segments) {
+            Some(item) => edit::insert(doc, &to_segments, item),
+            None => edit::set(doc, &to_segments, &val)
         };
+        if !moved || !path::insert(&mut self.table, &to_segments, val) {
+            return Err(ConfigError::InvalidKey(to.to_string()));
+        }
 
-        return match MythosConfig::read_dir(&path) {
-            Ok(data) => Some(data),
-            Err(err) => {
-                printerror!("Error reading contents of path. \"{err}\".");
-                return None;
+        let (from, to) = (path::normalize(from), path::normalize(to));
+        let moved: Vec<String> = self.origins.keys()
+            .filter(|key| *key == &from || key.starts_with(&format!("{from}.")))
+            .cloned()
+            .collect();
+        for key in moved {
+            if let Some(origin) = self.origins.remove(&key) {
+                self.origins.insert(format!("{to}{}", &key[from.len()..]), origin);
             }
         }
+        origin::prune(&mut self.origins, &self.table);
+        return Ok(());
+    }
+    pub fn config_version(&self) -> i64 {
+        //! The config_version key, or 0 if it is not set. See migrate(...).
+        return self.get_integer(migrate::VERSION_KEY, 0);
     }
-    fn read_dir(path: &PathBuf) -> Result<MythosConfig, std::io::Error> {
-        let contents = path.read_dir()?; 
-        let mut table: MythosConfig = MythosConfig(Table::new());
-
-        for item in contents {
-            let item = item?;
-            let path = item.path();
-            let key = path.file_stem().unwrap().to_str().unwrap();
-            if path.is_dir() {
-                table.extend(key, MythosConfig::read_dir(&path)?);
-            } else if let Some(file) = MythosConfig::read_file(&path) {
-                table.extend(key, file);
-            } else {
-                printwarn!("Tried to read {path:?} as config file. File was skipped.");
+    fn apply_migrations(&mut self, migrations: &Migrations) -> Result<(), ConfigError> {
+        for migration in migrations.pending(self.config_version()) {
+            for step in &migration.steps {
+                match step {
+                    Step::Rename { from, to } => self.rename(from, to)?,
+                    Step::MoveInto { key, section } => {
+                        let name = match path::parse(key).as_deref() {
+                            Some([.., path::Segment::Key(name)]) => path::join(&[name]),
+                            _ => return Err(ConfigError::InvalidKey(key.to_string()))
+                        };
+                        self.rename(key, &format!("{}.{name}", path::normalize(section)))?;
+                    },
+                    Step::Transform { key, transform } => match self.get(key).map(|x| transform(&value::from_toml(x))) {
+                        Some(Some(val)) => self.set(key, value::to_toml(val))?,
+                        Some(None) => {
+                            self.remove(key)?;
+                        },
+                        None => (),
+                    },
+                }
             }
+            self.set(migrate::VERSION_KEY, Value::Integer(migration.version))?;
         }
-        return Ok(table);
+        return Ok(());
     }
-    pub fn extend(&mut self, key: &str, other: MythosConfig) {
-        self.0.insert(key.to_string(), toml::Value::Table(other.0));
+    /**
+     * Upgrade the file this config was read from to migrations.latest().
+     * The original file is copied to <file>.v<old version>.bak before the upgraded file is written.
+     * Comments and formatting of keys that were not migrated are kept.
+     * Migrations see the file's own values, before includes, profiles, env overrides and interpolation,
+     * so e.g. "${mythos:DATA}/x" is written back as is. This config is migrated in memory as well.
+     * Returns the backup's path, or None if the config was already up to date.
+     * Only configs opened from a single TOML file can be migrated, see migrate_dry_run(...) to preview changes.
+     * Fails if the config has unsaved edits, save them first.
+     */
+    pub fn migrate(&mut self, migrations: &Migrations) -> Result<Option<PathBuf>, ConfigError> {
+        let old_version = self.config_version();
+        if migrations.pending(old_version).is_empty() {
+            return Ok(None);
+        }
+        if self.editor.is_edited() {
+            return Err(ConfigError::Migration("config has unsaved edits".into()));
+        }
+        let (path, mut file) = self.migration_source()?;
+        file.apply_migrations(migrations)?;
+        self.apply_migrations(migrations)?;
+
+        let file_name = path.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+        let backup = path.with_file_name(format!("{file_name}.v{old_version}.bak"));
+        std::fs::copy(&path, &backup).map_err(|err| ConfigError::io(&backup, err))?;
+        file.save_to(&path)?;
+        // This config's document was edited with resolved values, later saves should start from the file's.
+        self.editor = file.editor;
+        logger::info(&format!(
+            "Migrated {} from version {old_version} to {}. Backup saved to {}.",
+            path.display(), self.config_version(), backup.display()
+        ));
+        return Ok(Some(backup));
+    }
+    fn migration_source(&self) -> Result<(PathBuf, MythosConfig), ConfigError> {
+        //! The single TOML file this config was read from, parsed without includes or options.
+        let path = match (&self.source, &self.editor.seed) {
+            (Some(source), edit::Seed::File(path)) if source == path => path.to_owned(),
+            _ => return Err(ConfigError::Migration("config was not read from a single TOML file".into()))
+        };
+        let contents = std::fs::read_to_string(&path).map_err(|err| ConfigError::io(&path, err))?;
+        let table = toml::from_str::<Table>(&contents)
+            .map_err(|err| ConfigError::parse(&path, &contents, err.message(), err.span().map(|x| x.start)))?;
+        let mut file = MythosConfig::from_table(table);
+        file.source = Some(path.to_owned());
+        file.editor = edit::Editor::new(self.editor.util_name.as_deref(), edit::Seed::File(path.to_owned()));
+        return Ok((path, file));
+    }
+    pub fn diff(&self, other: &MythosConfig) -> ConfigDiff {
+        //! What changed from self to other, e.g. defaults.diff(&config) lists what a user has customized.
+        return diff::diff(&self.full_table(), &other.full_table());
+    }
+    pub fn migrate_dry_run(&self, migrations: &Migrations) -> Result<String, ConfigError> {
+        //! Print and return what migrate(...) would change, without changing anything.
+        //! Each changed key is printed as "- key = old" and/or "+ key = new".
+        //! As with migrate(...), a single TOML file is migrated as written. Other configs are migrated as read.
+        let before = match self.migration_source() {
+            Ok((_, file)) => file.table,
+            Err(_) => self.full_table()
+        };
+        let mut config = MythosConfig::from_table(before.clone());
+        config.apply_migrations(migrations)?;
+        let diff = diff::diff(&before, &config.table).to_string();
+        print!("{diff}");
+        return Ok(diff);
+    }
+    /**
+     * Write edits to the util's file in $MYTHOS_LOCAL_CONFIG_DIR, which is created if needed.
+     * Files in $MYTHOS_CONFIG_DIR are never written to. 
+     * If the config was read from one, the local file will start as a copy of it.
+     * Returns the path that was written to.
+     */
+    pub fn save(&mut self) -> Result<PathBuf, ConfigError> {
+        let path = self.editor.target()?;
+        self.save_to(&path)?;
+        return Ok(path);
+    }
+    pub fn save_to(&mut self, path: &Path) -> Result<(), ConfigError> {
+        self.load_lazy();
+        let doc = self.editor.document(&self.table)?;
+        return edit::write(doc, path);
     }
 
+    fn get(&self, key: &str) -> Option<&Value> {
+        return self.view().get(key);
+    }
+    pub fn view(&self) -> ConfigRef<'_> {
+        //! Borrow the config, e.g. to walk its subsections without copying them.
+        return ConfigRef::new(self);
+    }
     pub fn list_keys(&self) -> Vec<String> {
-        return self.0.keys().into_iter().map(|x| x.to_owned()).collect();
+        return self.view().list_keys();
     }
 
     pub fn get_subsection(&self, key: &str) -> Option<MythosConfig> {
-        return match &self.0.get(key) {
-            Some(Value::Table(val)) => {
-                Some(MythosConfig(val.to_owned()))
-            },
-            _ => None
-        };
+        //! Copy the table at key into its own config. See view().get_subsection(...) to borrow it instead.
+        return self.view().get_subsection(key).map(|x| x.to_config());
     }
 
     pub fn get_string(&self, key: &str, default_val: &str) -> String {
-        return match &self.0.get(key) {
-            Some(Value::String(val)) => val.to_owned(),
-            _ => default_val.to_string()
-        };
+        return self.view().get_string(key, default_val);
     }
     pub fn try_get_string(&self, key: &str) -> Option<String> {
-        return match &self.0.get(key) {
-            Some(Value::String(val)) => Some(val.to_owned()),
-            _ => None
-        };
+        return self.view().try_get_string(key);
     }
     pub fn force_get_string(&self, key: &str) -> Option<String> {
-        return match &self.0.get(key) {
-            Some(Value::String(val)) => Some(val.into()),
-            Some(Value::Float(val)) => Some(format!("{val}")),
-            Some(Value::Integer(val)) => Some(format!("{val}")),
-            Some(Value::Boolean(val)) => Some(format!("{val}")),
-            Some(Value::Datetime(val)) => Some(format!("{val}")),
-            Some(Value::Array(val)) => {
-                let arr: String = val.into_iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" ");
-                Some(arr)
-            },
-            Some(Value::Table(val)) => {
-                let tab: String = val.into_iter().map(|x| format!("{}:{}", x.0, x.1)).collect::<Vec<String>>().join(" ");
-                Some(tab)
-            },
-            None => Some("".into()),
-        }
+        return self.view().force_get_string(key);
     }
 
     pub fn get_integer(&self, key: &str, default_val: i64) -> i64 {
-        return match &self.0.get(key) {
-            Some(Value::Integer(val)) => val.to_owned(),
-            _ => default_val
-        };
+        return self.view().get_integer(key, default_val);
     }
     pub fn try_get_integer(&self, key: &str) -> Option<i64> {
-        return match &self.0.get(key) {
-            Some(Value::Integer(val)) => Some(val.to_owned()),
-            _ => None
-        };
+        return self.view().try_get_integer(key);
     }
 
     pub fn get_float(&self, key: &str, default_val: f64) -> f64 {
-        return match &self.0.get(key) {
-            Some(Value::Float(val)) => val.to_owned(),
-            _ => default_val
-        };
+        return self.view().get_float(key, default_val);
     }
     pub fn try_get_float(&self, key: &str) -> Option<f64> {
-        return match &self.0.get(key) {
-            Some(Value::Float(val)) => Some(val.to_owned()),
-            _ => None
-        };
+        return self.view().try_get_float(key);
     }
     pub fn get_boolean(&self, key: &str, default_val: bool) -> bool {
-        return match &self.0.get(key) {
-            Some(Value::Boolean(val)) => val.to_owned(),
-            _ => default_val
-        };
+        return self.view().get_boolean(key, default_val);
     }
     pub fn try_get_boolean(&self, key: &str) -> Option<bool> {
-        return match &self.0.get(key) {
-            Some(Value::Boolean(val)) => Some(val.to_owned()),
-            _ => None
-        };
+        return self.view().try_get_boolean(key);
     }
 
     pub fn get_datetime(&self, key: &str, default_val: &str) -> String{
-        return match &self.0.get(key) {
-            Some(Value::Datetime(val)) => val.to_string(),
-            _ => default_val.to_string()
-        };
+        return self.view().get_datetime(key, default_val);
     }
     pub fn try_get_datetime(&self, key: &str) -> Option<String> {
-        return match &self.0.get(key) {
-            Some(Value::Datetime(val)) => Some(val.to_string()),
-            _ => None 
-        };
+        return self.view().try_get_datetime(key);
     }
-    /* 
-     * While the other methods encapsulate/abstract the toml crate,
-     * these three methods cannot due to their datatypes.
-     */
-    pub fn get_array(&self, key: &str, default_val: Vec<Value>) -> Vec<Value> {
-        return match &self.0.get(key) {
-            Some(Value::Array(val)) => val.to_owned(),
-            _ => default_val
-        };
+    pub fn get_date(&self, key: &str, default_val: NaiveDate) -> NaiveDate {
+        return self.view().get_date(key, default_val);
     }
-    pub fn try_get_array(&self, key: &str) -> Option<Vec<Value>> {
-        return match &self.0.get(key) {
-            Some(Value::Array(val)) => Some(val.to_owned()),
-            _ => None
-        };
+    pub fn try_get_date(&self, key: &str) -> Option<NaiveDate> {
+        return self.view().try_get_date(key);
+    }
+    pub fn get_time(&self, key: &str, default_val: NaiveTime) -> NaiveTime {
+        return self.view().get_time(key, default_val);
+    }
+    pub fn try_get_time(&self, key: &str) -> Option<NaiveTime> {
+        return self.view().try_get_time(key);
+    }
+    pub fn get_naive_datetime(&self, key: &str, default_val: NaiveDateTime) -> NaiveDateTime {
+        return self.view().get_naive_datetime(key, default_val);
+    }
+    pub fn try_get_naive_datetime(&self, key: &str) -> Option<NaiveDateTime> {
+        return self.view().try_get_naive_datetime(key);
+    }
+    pub fn get_datetime_tz(&self, key: &str, default_val: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        return self.view().get_datetime_tz(key, default_val);
+    }
+    pub fn try_get_datetime_tz(&self, key: &str) -> Option<DateTime<FixedOffset>> {
+        return self.view().try_get_datetime_tz(key);
+    }
+    pub fn get_array(&self, key: &str, default_val: Vec<ConfigValue>) -> Vec<ConfigValue> {
+        return self.view().get_array(key, default_val);
+    }
+    pub fn try_get_array(&self, key: &str) -> Option<Vec<ConfigValue>> {
+        return self.view().try_get_array(key);
     }
     pub fn get_typed_array<'a, T>(&self, key: &str) -> Vec<T> where T: serde::Deserialize<'a> {
-        return match &self.0.get(key) {
-            Some(Value::Array(val)) => val.to_owned(),
-            _ => return vec![]
-        }.into_iter()
-            .filter_map(|x| x.try_into().ok())
-            .collect()
-    }
-    pub fn get_table(&self, key: &str, default_val: Table) -> Table {
-        return match &self.0.get(key) {
-            Some(Value::Table(val)) => val.to_owned(),
-            _ => default_val
-        };
+        return self.view().get_typed_array(key);
     }
-    pub fn try_get_table(&self, key: &str) -> Option<Table> {
-        return match &self.0.get(key) {
-            Some(Value::Table(val)) => Some(val.to_owned()),
-            _ => None
-        };
+    pub fn try_get_typed_array<T>(&self, key: &str) -> Result<Vec<T>, ConfigError> where T: serde::de::DeserializeOwned {
+        return self.view().try_get_typed_array(key);
+    }
+    pub fn get_table_array(&self, key: &str) -> Vec<MythosConfig> {
+        return self.view().get_table_array(key);
+    }
+    pub fn try_get_table_array(&self, key: &str) -> Option<Vec<MythosConfig>> {
+        return self.view().try_get_table_array(key);
+    }
+    pub fn get_table(&self, key: &str, default_val: ConfigTable) -> ConfigTable {
+        return self.view().get_table(key, default_val);
+    }
+    pub fn try_get_table(&self, key: &str) -> Option<ConfigTable> {
+        return self.view().try_get_table(key);
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        return self.view().contains_key(key);
+    }
+    pub fn type_of(&self, key: &str) -> Option<ValueKind> {
+        //! Kind of the value at key, or None if it is missing.
+        return self.view().type_of(key);
+    }
+    pub fn entries(&self) -> impl Iterator<Item = (String, ConfigValue)> + '_ {
+        //! Every top-level key and its value, sorted by key. See view().entries() for subsections.
+        return self.view().entries();
+    }
+
+    #[cfg(feature = "toml-values")]
+    pub fn get_toml_array(&self, key: &str, default_val: Vec<Value>) -> Vec<Value> {
+        return self.view().get_toml_array(key, default_val);
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_array(&self, key: &str) -> Option<Vec<Value>> {
+        return self.view().try_get_toml_array(key);
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_array_ref(&self, key: &str) -> Option<&[Value]> {
+        return self.view().try_get_toml_array_ref(key);
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn get_toml_table(&self, key: &str, default_val: Table) -> Table {
+        return self.view().get_toml_table(key, default_val);
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_table(&self, key: &str) -> Option<Table> {
+        return self.view().try_get_toml_table(key);
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_table_ref(&self, key: &str) -> Option<&Table> {
+        return self.view().try_get_toml_table_ref(key);
+    }
+
+    pub fn get_duration(&self, key: &str, default_val: Duration) -> Result<Duration, ConfigError> {
+        return self.view().get_duration(key, default_val);
+    }
+    pub fn try_get_duration(&self, key: &str) -> Result<Option<Duration>, ConfigError> {
+        return self.view().try_get_duration(key);
+    }
+    pub fn get_size(&self, key: &str, default_val: u64) -> Result<u64, ConfigError> {
+        return self.view().get_size(key, default_val);
+    }
+    pub fn try_get_size(&self, key: &str) -> Result<Option<u64>, ConfigError> {
+        return self.view().try_get_size(key);
+    }
+    pub fn get_path(&self, key: &str, default_val: &Path) -> Result<PathBuf, ConfigError> {
+        return self.view().get_path(key, default_val);
+    }
+    pub fn try_get_path(&self, key: &str) -> Result<Option<PathBuf>, ConfigError> {
+        return self.view().try_get_path(key);
+    }
+    pub fn get_color(&self, key: &str, default_val: Color) -> Result<Color, ConfigError> {
+        return self.view().get_color(key, default_val);
+    }
+    pub fn try_get_color(&self, key: &str) -> Result<Option<Color>, ConfigError> {
+        return self.view().try_get_color(key);
     }
 }
-fn try_get_file(path: &str, allow_dir: bool) -> Option<PathBuf> {
-    match clean_and_validate(dirs::expand_path(dirs::MythosDir::LocalConfig, path), allow_dir) {
-        Some(path) => return Some(path),
-        None => ()
+fn report(res: Result<MythosConfig, ConfigError>) -> Option<MythosConfig> {
+    //! Shared by the open* fns: print the error instead of returning it.
+    return match res {
+        Ok(config) => Some(config),
+        Err(err) => {
+            printerror!("{err}");
+            None
+        }
+    };
+}
+pub fn defaults_path(util_name: &str) -> PathBuf {
+    //! Where install_defaults(...) writes util_name's default config: $MYTHOS_DATA_DIR/util_name/defaults.toml
+    return dirs::expand_path(dirs::MythosDir::Data, util_name).join("defaults.toml");
+}
+pub fn probed_paths(path_snippet: &str, allow_dir: bool) -> Vec<PathBuf> {
+    //! Every path that open_file(...) (or open(...) if allow_dir) checks for path_snippet, in order.
+    //! Checking stops at the first match, which is the last path returned. If it doesn't exist, nothing matched.
+    let mut probed = Vec::new();
+    let _ = probe_file(path_snippet, allow_dir, &mut probed);
+    return probed;
+}
+fn try_get_file(path: &str, allow_dir: bool) -> Result<PathBuf, ConfigError> {
+    return probe_file(path, allow_dir, &mut Vec::new());
+}
+fn probe_file(path: &str, allow_dir: bool, probed: &mut Vec<PathBuf>) -> Result<PathBuf, ConfigError> {
+    //! Check LocalConfig, then Config. Every path that is checked is added to probed.
+    //! If neither contains a match, every path that was tried is returned in the error.
+    let local_err = match probe(dirs::expand_path(dirs::MythosDir::LocalConfig, path), allow_dir, probed) {
+        Ok(path) => return Ok(path),
+        Err(err) => err
+    };
+    let global_err = match probe(dirs::expand_path(dirs::MythosDir::Config, path), allow_dir, probed) {
+        Ok(path) => return Ok(path),
+        Err(err) => err
+    };
+
+    return match (local_err, global_err) {
+        (ConfigError::NotFound { tried: mut local, .. }, ConfigError::NotFound { tried: global, .. }) => {
+            local.extend(global);
+            Err(ConfigError::NotFound { path_snippet: path.to_string(), tried: local })
+        },
+        (err @ ConfigError::IsDirectory(_), _) => Err(err),
+        (_, err) => Err(err),
     };
-    return clean_and_validate(dirs::expand_path(dirs::MythosDir::Config, path), allow_dir);
 }
 /**
  * Caller can optionally omit file extension.
  * If {path} exists -> use that
  * Else -> try from list of valid extensions
  */
-fn clean_and_validate(path: PathBuf, allow_dir: bool) -> Option<PathBuf> {
+fn clean_and_validate(path: PathBuf, allow_dir: bool) -> Result<PathBuf, ConfigError> {
+    return probe(path, allow_dir, &mut Vec::new());
+}
+fn probe(path: PathBuf, allow_dir: bool, probed: &mut Vec<PathBuf>) -> Result<PathBuf, ConfigError> {
+    probed.push(path.clone());
     if path.exists() {
         if !allow_dir && path.is_dir() {
-            let err_msg = format!("'{:?}' is a directory", path);
-
             // Check CONFIG_DIR/util_name/config
-            let mut new_path = path;
-            new_path.push("config");
+            let new_path = path.join("config");
 
+            probed.push(new_path.clone());
             if new_path.exists() {
-                return Some(new_path);
+                return Ok(new_path);
             }
-            eprintln!("{}", err_msg);
-            return None;
+            return Err(ConfigError::IsDirectory(path));
         }
-        return Some(path);
+        return Ok(path);
     }
 
-    for ext in VALID_CONFIG_EXT {
+    let mut tried = vec![path.clone()];
+    for ext in VALID_CONFIG_EXT.iter() {
+        probed.push(path.with_extension(ext));
         if path.with_extension(ext).exists() {
-            return Some(path.with_extension(ext));
+            return Ok(path.with_extension(ext));
         }
+        tried.push(path.with_extension(ext));
     }
-    printerror!("Path {path:?} does not exist and doesn't contain any config-like files.");
-    return None;
+    return Err(ConfigError::NotFound { path_snippet: path.to_string_lossy().to_string(), tried });
 }
 
 #[cfg(test)]
@@ -267,11 +1039,32 @@ pub mod tests {
             std::env::set_var("MYTHOS_CONFIG_DIR", "tests/config");
         }
     }
+    /// An empty dir in temp_dir for one test, removed when dropped, even if the test panics.
+    pub struct Scratch(PathBuf);
+    impl Scratch {
+        pub fn new(test: &str) -> Scratch {
+            let dir = std::env::temp_dir().join(format!("mythos-core-{test}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            return Scratch(dir);
+        }
+    }
+    impl std::ops::Deref for Scratch {
+        type Target = Path;
+        fn deref(&self) -> &Path {
+            return &self.0;
+        }
+    }
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
     #[test]
     pub fn try_open_dir_as_file() {
         setup();
         let dir = try_get_file("empty_dir", false);
-        assert!(dir.is_none());
+        assert!(matches!(dir, Err(ConfigError::NotFound { .. })));
     }
     #[test]
     pub fn try_open_dir() {
@@ -293,6 +1086,628 @@ pub mod tests {
         assert_eq!(list3.try_get_integer("value"), Some(1));
     }
     #[test]
+    pub fn open_dir_options() {
+        setup();
+        let conf = MythosConfig::load("dir_tester").unwrap();
+        assert_eq!(conf.list_keys(), vec!["10-b", "2-a", "list", "templates"]);
+        assert_eq!(conf.try_get_string("script"), None);
+        assert_eq!(conf.try_get_integer("list.10-third.value"), Some(10));
+
+        let options = ConfigOptions { dir_arrays: true, ..Default::default() };
+        let conf = MythosConfig::load_with("dir_tester", &options).unwrap();
+        let values: Vec<i64> = conf.try_get_array("list").unwrap().iter().map(|x| x.as_table().unwrap()["value"].as_integer().unwrap()).collect();
+        assert_eq!(values, vec![1, 2, 10]);
+        assert!(conf.origin("list[0]").is_some());
+
+        let options = ConfigOptions {
+            raw_extensions: vec!["sh".into()],
+            raw_dirs: vec![PathBuf::from("templates")],
+            ..Default::default()
+        };
+        let conf = MythosConfig::load_with("dir_tester", &options).unwrap();
+        assert_eq!(conf.try_get_string("script"), Some("#!/bin/sh\necho 'not toml'\n".into()));
+        assert_eq!(conf.try_get_string("templates.page"), Some("<h1>{{ title }}</h1>\n".into()));
+        assert_eq!(conf.get_typed_array::<i64>("templates.logo"), vec![255, 0, 1]);
+
+        // Raw files are never interpolated, even when interpolation is on.
+        let options = ConfigOptions { interpolate: true, ..options };
+        let conf = MythosConfig::load_with("dir_tester", &options).unwrap();
+        assert_eq!(conf.try_get_string("templates.env"), Some("echo ${MYTHOS_NOT_SET_VAR} $$\n".into()));
+        assert_eq!(conf.origin("templates.env"), Some(&Origin::Raw(PathBuf::from("tests/lconfig/dir_tester/templates/env.tmpl"))));
+        let options = ConfigOptions { dir_arrays: true, ..options };
+        let conf = MythosConfig::load_with("dir_tester", &options).unwrap();
+        assert_eq!(conf.try_get_string("templates[0]"), Some("echo ${MYTHOS_NOT_SET_VAR} $$\n".into()));
+
+        match MythosConfig::load("dir_collision") {
+            Err(ConfigError::KeyCollision { key, paths }) => assert_eq!((key.as_str(), paths.len()), ("foo", 2)),
+            res => panic!("Expected KeyCollision, got {res:?}")
+        }
+        match MythosConfig::load("dir_cycle") {
+            Err(ConfigError::DirCycle(chain)) => assert_eq!(chain.first(), chain.last()),
+            res => panic!("Expected DirCycle, got {res:?}")
+        }
+    }
+    #[test]
+    pub fn borrow_sections() {
+        setup();
+        let conf = MythosConfig::open("abstract_config").unwrap();
+        let list1 = conf.view().get_subsection("list1").unwrap();
+        let list2 = list1.get_subsection("list1_list2").unwrap();
+        assert_eq!(list2.try_get_integer("config.value"), Some(11));
+        assert_eq!(list2.list_keys(), vec!["config", "list1_list2_dict1"]);
+        assert_eq!(list2.to_config().try_get_integer("config.value"), Some(11));
+        assert_eq!(conf.try_get_table("dict2").map(|x| x.len()), Some(2));
+    }
+    #[test]
+    pub fn read_dirs_lazily() {
+        setup();
+        let dir = Scratch::new("read_dirs_lazily");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.toml"), "value = 1").unwrap();
+        std::fs::write(dir.join("sub/b.toml"), "value = 1").unwrap();
+
+        let options = ConfigOptions { lazy_dirs: true, ..Default::default() };
+        let conf = MythosConfig::load_with(dir.to_str().unwrap(), &options).unwrap();
+        assert_eq!(conf.try_get_integer("a.value"), Some(1));
+        // Files are read when first accessed, so later changes are still picked up.
+        std::fs::write(dir.join("a.toml"), "value = 2").unwrap();
+        std::fs::write(dir.join("sub/b.toml"), "value = 2").unwrap();
+        assert_eq!(conf.try_get_integer("a.value"), Some(1));
+        assert_eq!(conf.view().list_keys(), vec!["a", "sub"]);
+        assert_eq!(conf.view().get_subsection("sub").unwrap().list_keys(), vec!["b"]);
+        assert_eq!(conf.try_get_integer("sub.b.value"), Some(2));
+        assert!(conf.origin("sub.b.value").is_some());
+
+        let mut conf = MythosConfig::load_with(dir.to_str().unwrap(), &options).unwrap();
+        conf.set_integer("a.other", 3).unwrap();
+        assert_eq!(conf.deserialize::<Table>().unwrap()["sub"]["b"]["value"], Value::Integer(2));
+        assert_eq!(conf.try_get_integer("a.value"), Some(2));
+
+        // Tables and iteration see the same values whether files are read lazily or not.
+        let lazy = MythosConfig::load_with(dir.to_str().unwrap(), &options).unwrap();
+        let eager = MythosConfig::load_with(dir.to_str().unwrap(), &ConfigOptions::default()).unwrap();
+        assert_eq!(lazy.try_get_table("sub"), eager.try_get_table("sub"));
+        assert_eq!(lazy.try_get_table("sub").unwrap().len(), 1);
+        assert_eq!(lazy.entries().collect::<Vec<_>>(), eager.entries().collect::<Vec<_>>());
+        assert_eq!((&lazy).into_iter().collect::<Vec<_>>(), (&eager).into_iter().collect::<Vec<_>>());
+        assert_eq!(lazy.view().get_subsection("sub").unwrap().entries().collect::<Vec<_>>(),
+            eager.view().get_subsection("sub").unwrap().entries().collect::<Vec<_>>());
+    }
+    #[test]
+    pub fn not_found_lists_tried_paths() {
+        setup();
+        let tried = match MythosConfig::load_file("nonameutil") {
+            Err(ConfigError::NotFound { tried, .. }) => tried,
+            _ => panic!("Expected NotFound")
+        };
+        assert!(tried.contains(&PathBuf::from("tests/lconfig/nonameutil.toml")));
+        assert!(tried.contains(&PathBuf::from("tests/config/nonameutil.conf")));
+    }
+    #[test]
+    pub fn parse_error_has_location() {
+        setup();
+        let err = MythosConfig::load_file("parse_error").unwrap_err();
+        match &err {
+            ConfigError::Parse { line, column, snippet, .. } => {
+                assert_eq!((*line, *column), (2, 7));
+                assert_eq!(snippet, "int = = 1");
+            },
+            _ => panic!("Expected Parse, got {err:?}")
+        }
+        assert!(err.to_string().ends_with("2 | int = = 1\n  |       ^"));
+    }
+    #[test]
+    pub fn open_layered() {
+        setup();
+        let conf = MythosConfig::load_layered("layered").unwrap();
+        assert_eq!(conf.try_get_string("name"), Some("local".into()));
+        assert_eq!(conf.try_get_integer("keep"), Some(1));
+
+        let section = conf.get_subsection("section").unwrap();
+        assert_eq!(section.try_get_integer("a"), Some(1));
+        assert_eq!(section.try_get_integer("b"), Some(20));
+        assert_eq!(conf.get_typed_array::<i64>("list"), vec![2, 3]);
+    }
+    #[test]
+    pub fn open_layered_with_options() {
+        setup();
+        let options = ConfigOptions {
+            array_merge: ArrayMerge::UniqueAppend,
+            layers: vec![PathBuf::from("tests/config/layered_extra")],
+            ..Default::default()
+        };
+        let conf = MythosConfig::load_layered_with("layered", &options).unwrap();
+        assert_eq!(conf.try_get_string("name"), Some("extra".into()));
+        assert_eq!(conf.get_typed_array::<i64>("list"), vec![1, 2, 3, 4]);
+
+        assert!(matches!(MythosConfig::load_layered("nonameutil"), Err(ConfigError::NotFound { .. })));
+    }
+    #[test]
+    pub fn embedded_defaults() {
+        setup();
+        let default = "name = \"default\"\nonly_default = true\n\n[section]\nc = 3\n";
+        let conf = MythosConfig::from_str(default).unwrap();
+        assert_eq!(conf.try_get_integer("section.c"), Some(3));
+        assert_eq!(conf.origin("section.c"), Some(&Origin::Embedded { line: Some(5) }));
+        assert!(matches!(MythosConfig::from_str("key = = 1"), Err(ConfigError::Parse { line: 1, .. })));
+
+        let options = ConfigOptions { default_config: Some(default.into()), ..Default::default() };
+        let conf = MythosConfig::load_layered_with("layered", &options).unwrap();
+        assert_eq!(conf.try_get_string("name"), Some("local".into()));
+        assert_eq!(conf.try_get_boolean("only_default"), Some(true));
+        assert_eq!(conf.try_get_integer("section.a"), Some(1));
+        assert_eq!(conf.try_get_integer("section.c"), Some(3));
+        let conf = MythosConfig::load_layered_with("nonameutil", &options).unwrap();
+        assert_eq!(conf.try_get_string("name"), Some("default".into()));
+    }
+    #[test]
+    pub fn install_defaults() {
+        setup();
+        let dir = Scratch::new("install_defaults");
+        let util_name = dir.join("nested/util");
+        let util_name = util_name.to_str().unwrap();
+
+        assert!(matches!(MythosConfig::ensure_default(util_name, "key = = 1"), Err(ConfigError::Parse { .. })));
+        assert!(!dir.join("nested").exists());
+        let path = MythosConfig::ensure_default(util_name, "value = 1").unwrap();
+        assert_eq!(path, Some(dir.join("nested/util.toml")));
+        assert_eq!(MythosConfig::ensure_default(util_name, "value = 2").unwrap(), None);
+        assert_eq!(MythosConfig::load(util_name).unwrap().try_get_integer("value"), Some(1));
+        assert_eq!(MythosConfig::ensure_default("config_tester", "value = 2").unwrap(), None);
+
+        let util_name = dir.join("data/util");
+        let util_name = util_name.to_str().unwrap();
+        assert!(matches!(MythosConfig::install_defaults(util_name, "key = = 1"), Err(ConfigError::Parse { .. })));
+        let path = MythosConfig::install_defaults(util_name, "value = 1").unwrap();
+        assert_eq!(path, dir.join("data/util/defaults.toml"));
+        assert_eq!(path, defaults_path(util_name));
+        assert_eq!(MythosConfig::install_defaults(util_name, "value = 2").unwrap(), path);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "value = 2");
+    }
+    #[test]
+    pub fn migrate_config() {
+        setup();
+        let migrations = Migrations::new()
+            .migration(Migration::new(3)
+                .transform("timeout", |x| Some(ConfigValue::Integer(x.as_integer()? * 1000)))
+                .transform("paths.shared", |x| Some(x.clone())))
+            .migration(Migration::new(2)
+                .rename("colour", "color")
+                .move_into("width", "display")
+                .rename("old_section", "section")
+                .rename("missing", "still_missing"));
+        let dir = Scratch::new("migrate_config");
+        let path = dir.join("util.toml");
+        std::fs::copy("tests/config/migrate_tester.toml", &path).unwrap();
+        let util_name = dir.join("util");
+        let util_name = util_name.to_str().unwrap();
+
+        // Migrations see the file as written, not the interpolated values.
+        let options = ConfigOptions { interpolate: true, ..Default::default() };
+        let conf = MythosConfig::load_file_with(util_name, &options).unwrap();
+        assert_eq!(conf.config_version(), 0);
+        let diff = conf.migrate_dry_run(&migrations).unwrap();
+        assert!(!diff.contains("paths.shared"));
+        assert!(diff.contains("- colour = \"red\"\n"));
+        assert!(diff.contains("+ display.width = 80\n"));
+        assert!(diff.contains("+ timeout = 5000\n"));
+        assert!(diff.contains("+ config_version = 3\n"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), std::fs::read_to_string("tests/config/migrate_tester.toml").unwrap());
+
+        let mut conf = MythosConfig::load_file_with(util_name, &options).unwrap();
+        conf.set_integer("width", 1).unwrap();
+        assert!(matches!(conf.migrate(&migrations), Err(ConfigError::Migration(_))));
+
+        let mut conf = MythosConfig::load_file_with(util_name, &options).unwrap();
+        let backup = conf.migrate(&migrations).unwrap();
+        assert_eq!(backup, Some(dir.join("util.toml.v0.bak")));
+        assert_eq!(conf.try_get_string("color"), Some("red".into()));
+        assert_eq!(conf.origin("section.key"), Some(&Origin::File { path: path.clone(), line: Some(8) }));
+
+        let mut conf = MythosConfig::load_file(util_name).unwrap();
+        assert_eq!(conf.config_version(), 3);
+        assert_eq!(conf.try_get_string("colour"), None);
+        assert_eq!(conf.try_get_integer("display.width"), Some(80));
+        assert_eq!(conf.try_get_integer("timeout"), Some(5000));
+        assert_eq!(conf.try_get_string("section.key"), Some("value".into()));
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("# Kept when the section is renamed"));
+        assert!(contents.contains("shared = \"${mythos:DATA}/x\""));
+        assert!(MythosConfig::load_file(backup.unwrap().to_str().unwrap()).unwrap().try_get_string("colour").is_some());
+        assert_eq!(conf.migrate(&migrations).unwrap(), None);
+
+        let mut conf = MythosConfig::open("abstract_config").unwrap();
+        assert!(matches!(conf.migrate(&migrations), Err(ConfigError::Migration(_))));
+    }
+    #[test]
+    pub fn get_units() {
+        setup();
+        let conf = MythosConfig::open("units_tester").unwrap();
+        assert_eq!(conf.get_duration("timeout", Duration::ZERO).unwrap(), Duration::from_secs(330));
+        assert_eq!(conf.get_duration("missing", Duration::ZERO).unwrap(), Duration::ZERO);
+        assert_eq!(conf.try_get_size("cache").unwrap(), Some(10 << 20));
+        assert_eq!(conf.try_get_path("notes").unwrap(), Some(dirs::expand_path(dirs::MythosDir::LocalData, "notes")));
+        assert_eq!(conf.get_color("accent", Color { r: 0, g: 0, b: 0, a: 255 }).unwrap().to_string(), "#ff8800");
+        assert_eq!(conf.try_get_color("missing").unwrap(), None);
+
+        let err = conf.get_duration("broken.timeout", Duration::ZERO).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid value for 'broken.timeout'"));
+        let broken = conf.view().get_subsection("broken").unwrap();
+        assert!(matches!(broken.try_get_color("accent"), Err(ConfigError::InvalidValue { key, .. }) if key == "broken.accent"));
+        assert!(conf.try_get_size("timeout").is_err());
+    }
+    #[test]
+    pub fn track_origins() {
+        setup();
+        let mut conf = MythosConfig::load_layered("layered").unwrap();
+        let origin = |conf: &MythosConfig, key: &str| conf.origin(key).map(|x| x.to_string());
+        assert_eq!(origin(&conf, "name"), Some("tests/lconfig/layered.toml:1".into()));
+        assert_eq!(origin(&conf, "keep"), Some("tests/config/layered.toml:2".into()));
+        assert_eq!(origin(&conf, "list[1]"), Some("tests/lconfig/layered.toml:2".into()));
+        assert_eq!(origin(&conf.get_subsection("section").unwrap(), "a"), Some("tests/config/layered.toml:6".into()));
+        assert_eq!(origin(&conf, "section"), None);
+
+        conf.apply_defaults(&ConfigSchema::new().key(KeySpec::new("new", ValueKind::Integer).default(1)));
+        assert_eq!(conf.origin("new"), Some(&Origin::Default));
+        conf.set_integer("keep", 2).unwrap();
+        assert_eq!(conf.origin("keep"), Some(&Origin::Set));
+
+        let conf = MythosConfig::load_file("include_tester").unwrap();
+        assert!(origin(&conf, "base").unwrap().starts_with("tests/config/include/base.toml:"));
+
+        let probed = probed_paths("layered", false);
+        assert_eq!(probed.first(), Some(&PathBuf::from("tests/lconfig/layered")));
+        assert_eq!(probed.last(), Some(&PathBuf::from("tests/lconfig/layered.toml")));
+        assert_eq!(probed_paths("nonameutil", false).len(), (VALID_CONFIG_EXT.len() + 1) * 2);
+    }
+    #[derive(Debug, serde_derive::Deserialize)]
+    struct Tester {
+        int: i64,
+        string: String,
+        #[serde(default)]
+        missing: i64,
+        table: TesterTable,
+    }
+    #[derive(Debug, serde_derive::Deserialize)]
+    struct TesterTable {
+        int1: String,
+    }
+    #[derive(Debug, serde_derive::Deserialize)]
+    struct TesterFlat {
+        int: i64,
+        float: f64,
+        #[serde(default = "default_width")]
+        width: i64,
+    }
+    fn default_width() -> i64 { 80 }
+
+    #[test]
+    pub fn deserialize_config() {
+        setup();
+        let tester: TesterFlat = MythosConfig::open_as("config_tester").unwrap();
+        assert_eq!(tester.int, 1);
+        assert_eq!(tester.float, 1.1);
+        assert_eq!(tester.width, 80);
+
+        match MythosConfig::open_as::<Tester>("config_tester") {
+            Err(ConfigError::Deserialize { key, .. }) => assert_eq!(key, "table.int1"),
+            res => panic!("Expected Deserialize error, got {res:?}")
+        }
+    }
+    #[test]
+    pub fn apply_profiles() {
+        setup();
+        let conf = MythosConfig::load_file("profile_tester").unwrap();
+        assert_eq!(conf.profile(), None);
+        assert_eq!(conf.try_get_string("editor"), Some("vim".into()));
+
+        let options = ConfigOptions { profile: Some("work".into()), ..Default::default() };
+        let conf = MythosConfig::load_file_with("profile_tester", &options).unwrap();
+        assert_eq!(conf.profile(), Some("work"));
+        assert_eq!(conf.try_get_string("editor"), Some("code".into()));
+        assert_eq!(conf.try_get_string("theme"), Some("dark".into()));
+        assert_eq!(conf.try_get_string("server.host"), Some("work.example.com".into()));
+        assert_eq!(conf.try_get_boolean("server.debug"), Some(false));
+        assert_eq!(conf.try_get_string("inherits"), None);
+        assert_eq!(conf.origin("server.host"), Some(&Origin::File { path: "tests/config/profile_tester.toml".into(), line: Some(17) }));
+
+        let options = ConfigOptions { profile: Some("work".into()), array_merge: ArrayMerge::Append, ..Default::default() };
+        let conf = MythosConfig::load_file_with("profile_tester", &options).unwrap();
+        assert_eq!(conf.try_get_array("ports"), Some(vec![ConfigValue::Integer(80), ConfigValue::Integer(8080)]));
+
+        let options = ConfigOptions { profile: Some("broken".into()), ..Default::default() };
+        assert!(matches!(MythosConfig::load_file_with("profile_tester", &options), Err(ConfigError::InvalidProfile { .. })));
+
+        unsafe {
+            std::env::set_var("MYTHOS_PROFILE", "home");
+        }
+        let conf = MythosConfig::load_file("profile_tester").unwrap();
+        assert_eq!(conf.try_get_string("theme"), Some("light".into()));
+        // Utils that don't define the shared profile ignore it.
+        assert!(MythosConfig::load_file("env_tester").unwrap().profile().is_none());
+
+        unsafe {
+            std::env::set_var("MYTHOS_PROFILE_TESTER_PROFILE", "work");
+        }
+        let conf = MythosConfig::load_file("profile_tester").unwrap();
+        assert_eq!(conf.profile(), Some("work"));
+        let options = ConfigOptions { profile: Some("base".into()), ..Default::default() };
+        assert_eq!(MythosConfig::load_file_with("profile_tester", &options).unwrap().profile(), Some("base"));
+        unsafe {
+            std::env::remove_var("MYTHOS_PROFILE_TESTER_PROFILE");
+            std::env::remove_var("MYTHOS_PROFILE");
+        }
+    }
+    #[test]
+    pub fn get_nested_value() {
+        setup();
+        let conf = MythosConfig::open("abstract_config").unwrap();
+        assert_eq!(conf.try_get_integer("dict2.dict3.value"), Some(1000));
+        assert_eq!(conf.try_get_integer("list1.list1_list2.config.value"), Some(11));
+        assert_eq!(conf.get_subsection("list1.list1_dict1").unwrap().try_get_integer("value"), Some(9));
+
+        let conf = MythosConfig::open_file("config_tester").unwrap();
+        assert_eq!(conf.try_get_integer("array[1]"), Some(1));
+        assert_eq!(conf.get_integer("table.int2", -1), 2);
+        assert_eq!(conf.try_get_integer("table.int3"), None);
+    }
+    #[test]
+    pub fn env_overrides() {
+        setup();
+        unsafe {
+            std::env::set_var("MYTHOS_ENV_TESTER__TABLE__INT1", "5");
+            std::env::set_var("MYTHOS_ENV_TESTER__NEW_KEY", "hello world");
+        }
+        let conf = MythosConfig::load_file("env_tester").unwrap();
+        assert_eq!(conf.try_get_integer("table.int1"), Some(1));
+        assert_eq!(conf.env_source("table.int1"), None);
+
+        let options = ConfigOptions { env_overrides: true, ..Default::default() };
+        let conf = MythosConfig::load_with("env_tester", &options).unwrap();
+        assert_eq!(conf.try_get_integer("table.int1"), Some(5));
+        assert_eq!(conf.try_get_integer("table.int2"), Some(2));
+        assert_eq!(conf.try_get_string("new_key"), Some("hello world".into()));
+        assert_eq!(conf.env_source("table.int1"), Some("MYTHOS_ENV_TESTER__TABLE__INT1"));
+        assert_eq!(conf.get_subsection("table").unwrap().env_source("int1"), Some("MYTHOS_ENV_TESTER__TABLE__INT1"));
+    }
+    #[test]
+    pub fn edit_and_save() {
+        setup();
+        let mut conf = MythosConfig::load_file("edit_tester").unwrap();
+        conf.set_integer("int", 2).unwrap();
+        conf.set_string("table.new", "value").unwrap();
+        conf.set_array("new_table.array", vec![ConfigValue::from(1)]).unwrap();
+        assert!(conf.remove("table.removed").unwrap());
+        assert!(!conf.remove("table.removed").unwrap());
+        assert!(matches!(conf.set_integer("int.sub", 1), Err(ConfigError::InvalidKey(_))));
+        assert_eq!(conf.try_get_integer("int"), Some(2));
+        assert_eq!(conf.try_get_string("table.new"), Some("value".into()));
+
+        let dir = Scratch::new("edit_and_save");
+        conf.save_to(&dir.join("edit_tester.toml")).unwrap();
+        let contents = std::fs::read_to_string(dir.join("edit_tester.toml")).unwrap();
+        assert_eq!(contents, "# Comment about int\nint = 2 # trailing comment\nbool = true\n\n[table]\n# Comment about kept\nkept = 1\nnew = \"value\"\n\n[new_table]\narray = [1]\n");
+
+        // Saves to LocalConfig, never Config.
+        assert_eq!(conf.editor.target().unwrap(), PathBuf::from("tests/lconfig/edit_tester.toml"));
+    }
+    #[test]
+    pub fn failed_set_keeps_document() {
+        setup();
+        // base only exists in an include, so the document would accept base.sub while the table rejects it.
+        let mut conf = MythosConfig::load_file("include_tester").unwrap();
+        assert!(matches!(conf.set_integer("base.sub", 1), Err(ConfigError::InvalidKey(_))));
+        assert_eq!(conf.try_get_integer("base"), Some(1));
+
+        let dir = Scratch::new("failed_set_keeps_document");
+        conf.save_to(&dir.join("include_tester.toml")).unwrap();
+        let contents = std::fs::read_to_string(dir.join("include_tester.toml")).unwrap();
+        assert!(!contents.contains("sub"));
+    }
+    #[test]
+    pub fn open_ini_and_conf() {
+        setup();
+        let ini = MythosConfig::load_file("ini_tester").unwrap();
+        assert_eq!(ini.try_get_string("top"), Some("level".into()));
+        assert_eq!(ini.try_get_string("section.name"), Some("John Doe".into()));
+        assert_eq!(ini.try_get_string("section.sub.value"), Some("42".into()));
+
+        let vars = MythosConfig::load_file("vars_tester").unwrap();
+        assert_eq!(vars.try_get_string("MYTHOS_CONFIG_DIR"), Some("/etc/mythos".into()));
+        assert_eq!(vars.try_get_string("MYTHOS_LOCAL_DATA_DIR"), Some("$HOME/.local/share/mythos".into()));
+
+        // Still TOML, even though the extension is .conf
+        let conf = MythosConfig::load_file("config_tester").unwrap();
+        assert_eq!(conf.try_get_integer("int"), Some(1));
+    }
+    #[test]
+    pub fn save_non_toml() {
+        setup();
+        // A saved ini_save_tester.toml would be hidden by ini_save_tester.ini, which is probed first.
+        let mut ini = MythosConfig::load_file("ini_save_tester").unwrap();
+        ini.set_string("a", "2").unwrap();
+        assert!(matches!(ini.save(), Err(ConfigError::NoSaveTarget)));
+        let mut layered = MythosConfig::load_layered("ini_save_tester").unwrap();
+        layered.set_string("a", "2").unwrap();
+        assert!(matches!(layered.save(), Err(ConfigError::NoSaveTarget)));
+        assert!(!PathBuf::from("tests/lconfig/ini_save_tester.toml").exists());
+
+        let dir = Scratch::new("save_non_toml");
+        ini.save_to(&dir.join("ini_save_tester.toml")).unwrap();
+        let contents = std::fs::read_to_string(dir.join("ini_save_tester.toml")).unwrap();
+        assert!(contents.contains("a = \"2\""));
+    }
+    #[test]
+    pub fn save_values_as_read() {
+        setup();
+        let dir = Scratch::new("save_values_as_read");
+        std::fs::write(dir.join("raw.ini"), "path = ${HOME}/x\n").unwrap();
+        let options = ConfigOptions { interpolate: true, ..Default::default() };
+        let mut conf = MythosConfig::load_path_with(&dir.join("raw.ini"), &options).unwrap();
+        conf.apply_defaults(&ConfigSchema::new().key(KeySpec::new("width", ValueKind::Integer).default(80)));
+        assert_eq!(conf.try_get_integer("width"), Some(80));
+        assert_ne!(conf.try_get_string("path"), Some("${HOME}/x".into()));
+        conf.set_string("name", "x").unwrap();
+        conf.save_to(&dir.join("raw.toml")).unwrap();
+        let contents = std::fs::read_to_string(dir.join("raw.toml")).unwrap();
+        assert_eq!(contents, "path = \"${HOME}/x\"\nname = \"x\"\n");
+    }
+    #[test]
+    pub fn coerce_getters() {
+        setup();
+        let mut ini = MythosConfig::load_file("ini_tester").unwrap();
+        assert_eq!(ini.coercion(), Coercion::Strict);
+        assert_eq!(ini.try_get_integer("section.sub.value"), None);
+        ini.set_coercion(Coercion::Lenient);
+        assert_eq!(ini.try_get_integer("section.sub.value"), Some(42));
+        assert_eq!(ini.try_get_float("section.sub.value"), Some(42.0));
+        assert_eq!(ini.get_subsection("section.sub").unwrap().try_get_integer("value"), Some(42));
+
+        let options = ConfigOptions { coercion: Coercion::Numeric, ..Default::default() };
+        let conf = MythosConfig::open_with("config_tester", &options).unwrap();
+        assert_eq!(conf.try_get_float("int"), Some(1.0));
+        assert_eq!(conf.try_get_string("int"), None);
+    }
+    #[test]
+    pub fn diff_configs() {
+        setup();
+        let global = MythosConfig::load_path(Path::new("tests/config/layered")).unwrap();
+        let local = MythosConfig::load_file("layered").unwrap();
+        let diff = global.diff(&local);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed, vec![("keep".into(), 1.into()), ("section.a".into(), 1.into())]);
+        assert_eq!(diff.changed, vec![
+            ("list".into(), vec![1, 2].into(), vec![2, 3].into()),
+            ("name".into(), "global".into(), "local".into()),
+            ("section.b".into(), 2.into(), 20.into()),
+        ]);
+        assert!(local.diff(&local).is_empty());
+        assert!(matches!(MythosConfig::load_path(Path::new("tests/config/missing")), Err(ConfigError::NotFound { .. })));
+    }
+    #[cfg(all(feature = "json", feature = "yaml"))]
+    #[test]
+    pub fn open_mixed_formats() {
+        setup();
+        let conf = MythosConfig::load_file("json_tester").unwrap();
+        assert_eq!(conf.try_get_integer("table.int1"), Some(1));
+
+        let conf = MythosConfig::load("mixed_config").unwrap();
+        assert_eq!(conf.try_get_integer("toml_file.value"), Some(1));
+        assert_eq!(conf.try_get_integer("json_file.value"), Some(2));
+        assert_eq!(conf.try_get_integer("yaml_file.value"), Some(3));
+        assert_eq!(conf.try_get_string("yaml_file.list[1]"), Some("b".into()));
+    }
+    fn tester_schema() -> ConfigSchema {
+        return ConfigSchema::new()
+            .key(KeySpec::new("int", ValueKind::Integer).required().range(0.0, 10.0))
+            .key(KeySpec::new("float", ValueKind::Float).range(0.0, 1.0))
+            .key(KeySpec::new("string", ValueKind::String).allowed(["a", "b"]))
+            .key(KeySpec::new("bool", ValueKind::Integer))
+            .key(KeySpec::new("missing", ValueKind::String).required())
+            .key(KeySpec::new("table.int3", ValueKind::Integer).default(3).description("Has a default"));
+    }
+    #[test]
+    pub fn validate_schema() {
+        setup();
+        let mut config = MythosConfig::open_file("config_tester").unwrap();
+        let violations = config.validate(&tester_schema()).unwrap_err();
+        let violations: Vec<(&str, &Violation)> = violations.iter().map(|x| (x.key.as_str(), &x.violation)).collect();
+        assert_eq!(violations, vec![
+            ("float", &Violation::OutOfRange { min: Some(0.0), max: Some(1.0) }),
+            ("string", &Violation::NotAllowed { allowed: vec!["a".into(), "b".into()] }),
+            ("bool", &Violation::WrongType { expected: ValueKind::Integer, found: ValueKind::Boolean }),
+            ("missing", &Violation::Missing),
+        ]);
+
+        let err = config.validate(&tester_schema()).unwrap_err().remove(0);
+        assert_eq!(err.file, Some(PathBuf::from("tests/lconfig/config_tester.toml")));
+        assert_eq!(err.to_string(), "tests/lconfig/config_tester.toml: 'float' = 1.1 is outside of range [0, 1]");
+
+        assert_eq!(config.try_get_integer("table.int3"), None);
+        config.apply_defaults(&tester_schema());
+        assert_eq!(config.try_get_integer("table.int3"), Some(3));
+
+        // Defaults are never saved, whatever the document is built from.
+        config.set_integer("int", 2).unwrap();
+        assert!(!config.editor.document(&config.table).unwrap().to_string().contains("int3"));
+        let mut embedded = MythosConfig::from_str("a = 1").unwrap();
+        embedded.apply_defaults(&ConfigSchema::new().key(KeySpec::new("width", ValueKind::Integer).default(80)));
+        embedded.set_integer("b", 2).unwrap();
+        assert_eq!(embedded.editor.document(&embedded.table).unwrap().to_string(), "a = 1\nb = 2\n");
+    }
+    #[test]
+    pub fn watch_for_changes() {
+        setup();
+        let dir = Scratch::new("watch_for_changes");
+        // An absolute util name replaces the config dirs.
+        let path = dir.join("watch_tester.toml");
+        std::fs::write(&path, "value = 1").unwrap();
+        let (watcher, receiver) = watch_channel(&dir.join("watch_tester").to_string_lossy()).unwrap();
+        let timeout = std::time::Duration::from_secs(5);
+
+        // Replace the file the way editors do.
+        std::fs::write(dir.join(".watch_tester.toml.tmp"), "value = 2").unwrap();
+        std::fs::rename(dir.join(".watch_tester.toml.tmp"), &path).unwrap();
+        let config = receiver.recv_timeout(timeout);
+
+        std::fs::write(&path, "value = = 3").unwrap();
+        let err = receiver.recv_timeout(timeout);
+        drop(watcher);
+
+        // The config's dir doesn't exist yet when watching starts.
+        let missing = dir.join("missing");
+        let (watcher, receiver) = watch_channel(&missing.join("watch_tester").to_string_lossy()).unwrap();
+        std::fs::create_dir(&missing).unwrap();
+        std::fs::write(missing.join("watch_tester.toml"), "value = 4").unwrap();
+        let created = receiver.recv_timeout(timeout);
+        drop(watcher);
+
+        assert_eq!(config.unwrap().unwrap().try_get_integer("value"), Some(2));
+        assert!(matches!(err, Ok(Err(ConfigError::Parse { .. }))));
+        assert_eq!(created.unwrap().unwrap().try_get_integer("value"), Some(4));
+    }
+    #[test]
+    pub fn read_includes() {
+        setup();
+        let config = MythosConfig::load_file("include_tester").unwrap();
+        assert_eq!(config.try_get_string("own"), Some("own".into()));
+        assert_eq!(config.try_get_string("table.value"), Some("own".into()));
+        assert_eq!(config.try_get_integer("table.base_value"), Some(1));
+        assert_eq!(config.try_get_integer("base"), Some(1));
+        assert_eq!(config.try_get_integer("extra"), Some(2));
+        assert_eq!(config.try_get_array("include"), None);
+
+        match MythosConfig::load_file("include/cycle_a") {
+            Err(ConfigError::IncludeCycle(chain)) => {
+                let names: Vec<&str> = chain.iter().map(|x| x.file_name().unwrap().to_str().unwrap()).collect();
+                assert_eq!(names, vec!["cycle_a.toml", "cycle_b.toml", "cycle_a.toml"]);
+            },
+            res => panic!("Expected IncludeCycle, got {res:?}")
+        }
+        match MythosConfig::load_file("include/bad_parent") {
+            Err(ConfigError::Include { chain, source }) => {
+                assert_eq!(chain.len(), 2);
+                assert!(chain[1].ends_with("tests/config/parse_error.toml"));
+                assert!(matches!(*source, ConfigError::Parse { .. }));
+            },
+            res => panic!("Expected Include, got {res:?}")
+        }
+    }
+    #[test]
+    pub fn interpolate_values() {
+        setup();
+        let data = dirs::expand_mythos_shortcut("DATA", "").unwrap();
+        let options = ConfigOptions { interpolate: true, ..Default::default() };
+        let config = MythosConfig::load_file_with("interpolate_tester", &options).unwrap();
+        assert_eq!(config.try_get_string("data"), Some(format!("{}/interpolate", data.display())));
+        assert_eq!(config.try_get_string("price"), Some("$5".into()));
+
+        // Off by default.
+        let config = MythosConfig::load_file("interpolate_tester").unwrap();
+        assert_eq!(config.try_get_string("data"), Some("${mythos:DATA}/${self:name}".into()));
+        assert_eq!(config.try_get_string("price"), Some("$$5".into()));
+    }
+    #[test]
     pub fn try_open_file() {
         setup();
         let conf = MythosConfig::open("config_tester").unwrap();
@@ -302,20 +1717,20 @@ pub mod tests {
     pub fn get_file_with_no_ext() {
         setup();
         let root = dirs::expand_path(dirs::MythosDir::Config, "config_tester");
-        assert_eq!(clean_and_validate(root, false), Some(PathBuf::from("tests/config/config_tester.conf")));
+        assert_eq!(clean_and_validate(root, false).ok(), Some(PathBuf::from("tests/config/config_tester.conf")));
         let root = dirs::expand_path(dirs::MythosDir::Config, "arachne");
-        assert_eq!(clean_and_validate(root, false), Some(PathBuf::from("tests/config/arachne")));
+        assert_eq!(clean_and_validate(root, false).ok(), Some(PathBuf::from("tests/config/arachne")));
     }
     #[test]
     pub fn defaults_to_local_config() {
         setup();
-        let file = try_get_file("config_tester", false);
+        let file = try_get_file("config_tester", false).ok();
         assert_eq!(file, Some(PathBuf::from("tests/lconfig/config_tester.toml")));
     }
     #[test]
     pub fn get_file_named_config() {
         setup();
-        let file = try_get_file("config_tester_dir", false);
+        let file = try_get_file("config_tester_dir", false).ok();
         assert_eq!(file, Some(PathBuf::from("tests/lconfig/config_tester_dir/config")));
     }
     #[test]
@@ -329,11 +1744,86 @@ pub mod tests {
         assert_eq!(config.get_boolean("bool", false), true);
 
         assert_eq!(config.get_datetime("date", "2000-01-01"), "1970-01-01");
+        assert_eq!(config.try_get_date("date"), NaiveDate::from_ymd_opt(1970, 1, 1));
+        assert_eq!(config.try_get_naive_datetime("date"), None);
+
+        let mut strings = MythosConfig::from_str("when = \"1979-05-27T07:32:00Z\"\nint = \"42\"").unwrap();
+        assert_eq!(strings.try_get_datetime_tz("when"), None);
+        strings.set_parse_datetime_strings(true);
+        assert_eq!(strings.try_get_datetime_tz("when").map(|x| x.timestamp()), Some(296638320));
+        assert_eq!(strings.try_get_datetime("when"), Some("1979-05-27T07:32:00Z".into()));
+        assert_eq!(strings.try_get_integer("int"), None);
         assert_eq!(
-            config.get_array("array", vec![Value::Integer(-1), Value::Integer(-2)]),
-            vec![Value::Integer(0), Value::Integer(1)]
+            config.get_array("array", vec![ConfigValue::Integer(-1), ConfigValue::Integer(-2)]),
+            vec![ConfigValue::Integer(0), ConfigValue::Integer(1)]
             );
-        assert_eq!(config.get_table("table", Table::new())["int1"], Value::from(1));
+        assert_eq!(config.get_table("table", ConfigTable::new())["int1"], ConfigValue::from(1));
+    }
+    #[test]
+    pub fn iterate_values() {
+        setup();
+        let conf = MythosConfig::open("abstract_config").unwrap();
+        let keys: Vec<String> = conf.entries().map(|x| x.0).collect();
+        assert_eq!(keys, conf.list_keys());
+        assert!(conf.contains_key("dict2.dict3.value"));
+        assert!(!conf.contains_key("dict2.missing"));
+        assert_eq!(conf.type_of("dict2"), Some(ValueKind::Table));
+        assert_eq!(conf.type_of("dict2.value"), Some(ValueKind::Integer));
+        assert_eq!(conf.type_of("missing"), None);
+
+        let dict2 = conf.view().get_subsection("dict2").unwrap();
+        let entries: Vec<(String, ConfigValue)> = dict2.into_iter().collect();
+        assert_eq!(entries[1], ("value".into(), ConfigValue::Integer(100)));
+        for (key, val) in &conf {
+            assert_eq!(conf.type_of(&key), Some(val.kind()));
+        }
+        let owned: ConfigTable = conf.into_iter().collect();
+        assert_eq!(owned["dict2"].as_table().unwrap()["dict3"], ConfigValue::Table(ConfigTable::from([("value".into(), 1000.into())])));
+    }
+    #[cfg(feature = "toml-values")]
+    #[test]
+    pub fn get_toml_values() {
+        setup();
+        let config = MythosConfig::open_file("config_tester").unwrap();
+        assert_eq!(config.try_get_toml_array_ref("array"), Some(&[Value::Integer(0), Value::Integer(1)][..]));
+        assert_eq!(config.get_toml_table("table", Table::new())["int1"], Value::from(1));
+        assert_eq!(ConfigValue::from(Value::from(1)), ConfigValue::Integer(1));
+    }
+    #[test]
+    pub fn typed_arrays() {
+        setup();
+        #[derive(Deserialize, Debug)]
+        struct Server { #[allow(dead_code)] name: String, #[allow(dead_code)] port: i64 }
+
+        let conf = MythosConfig::open("array_tester").unwrap();
+        assert_eq!(conf.get_typed_array::<i64>("mixed"), vec![1, 3]);
+        assert_eq!(conf.try_get_typed_array::<i64>("ports").unwrap(), vec![80, 443]);
+        match conf.try_get_typed_array::<i64>("mixed") {
+            Err(ConfigError::Deserialize { key, message }) => {
+                assert_eq!(key, "mixed[1]");
+                assert!(message.contains("\"2\""));
+            },
+            res => panic!("Expected Deserialize error, got {res:?}")
+        }
+        match conf.try_get_typed_array::<Server>("servers") {
+            Err(ConfigError::Deserialize { key, .. }) => assert_eq!(key, "servers[1].port"),
+            res => panic!("Expected Deserialize error, got {res:?}")
+        }
+        assert!(matches!(conf.try_get_typed_array::<i64>("missing"), Err(ConfigError::MissingKey(key)) if key == "missing"));
+        assert!(matches!(conf.try_get_typed_array::<i64>("servers[0]"), Err(ConfigError::InvalidValue { .. })));
+
+        let mut lenient = MythosConfig::open("array_tester").unwrap();
+        lenient.set_coercion(Coercion::Lenient);
+        assert_eq!(lenient.get_typed_array::<i64>("mixed"), vec![1, 2, 3]);
+        assert_eq!(lenient.try_get_typed_array::<String>("mixed").unwrap(), vec!["1", "2", "3"]);
+
+        let servers = conf.get_table_array("servers");
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].try_get_string("name"), Some("web".into()));
+        assert_eq!(servers[1].try_get_string("port"), Some("5432".into()));
+        assert_eq!(servers[1].origin("name").map(|x| x.to_string()), Some("tests/config/array_tester.toml:4".into()));
+        assert!(conf.try_get_table_array("ports").is_none());
+        assert!(conf.get_table_array("missing").is_empty());
     }
     #[test]
     pub fn try_get_value() {
@@ -0,0 +1,253 @@
+/*!
+ * How getters treat values of the wrong type, see MythosConfig::set_coercion(...) and ConfigOptions::coercion.
+ *
+ * Getter               | Strict             | Numeric            | Lenient
+ * get_integer            integers             integers             + whole floats, strings like "42"
+ * get_float              floats               + integers           + strings like "1.5"
+ * get_boolean            booleans             booleans             + "true"/"false", "yes"/"no", "on"/"off", "1"/"0", 1/0
+ * get_string             strings              strings              + integers, floats and booleans
+ * get_datetime           datetimes            datetimes            + strings in any TOML datetime format
+ * get_date               local dates          local dates          + strings like "1979-05-27"
+ * get_time               local times          local times          + strings like "07:32:00"
+ * get_naive_datetime     local datetimes      local datetimes      + strings like "1979-05-27T07:32:00"
+ * get_datetime_tz        offset datetimes     offset datetimes     + RFC 3339 strings, e.g. "1979-05-27T07:32:00Z"
+ *
+ * Datetime getters also parse strings with ConfigOptions::parse_datetime_strings, without the rest of Lenient.
+ * Each chrono getter only accepts its own kind of TOML datetime, e.g. get_date(...) is None for "1979-05-27T07:32:00".
+ *
+ * get_typed_array(...) and try_get_typed_array(...) apply the policy to each element that doesn't fit the array's type,
+ * e.g. ["1", "2"] is a Vec<i64> when Lenient.
+ *
+ * Every coercion is logged at debug level.
+ */
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use toml::Value;
+use toml::value::{Datetime, Offset};
+use crate::logger;
+use super::ValueKind;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Coercion {
+    /// Values must already have the requested type.
+    #[default]
+    Strict,
+    /// Integers can be read as floats.
+    Numeric,
+    /// Numeric, plus strings are parsed and scalars can be read as strings.
+    /// Useful for values from environment variables and INI files, which are often strings.
+    Lenient,
+}
+
+pub(crate) fn integer(val: &Value, policy: Coercion, key: &str) -> Option<i64> {
+    if let Value::Integer(val) = val {
+        return Some(*val);
+    }
+    return logged(val, to_integer(val, policy), ValueKind::Integer, key);
+}
+
+pub(crate) fn float(val: &Value, policy: Coercion, key: &str) -> Option<f64> {
+    if let Value::Float(val) = val {
+        return Some(*val);
+    }
+    return logged(val, to_float(val, policy), ValueKind::Float, key);
+}
+
+pub(crate) fn boolean(val: &Value, policy: Coercion, key: &str) -> Option<bool> {
+    if let Value::Boolean(val) = val {
+        return Some(*val);
+    }
+    return logged(val, to_boolean(val, policy), ValueKind::Boolean, key);
+}
+
+pub(crate) fn string(val: &Value, policy: Coercion, key: &str) -> Option<String> {
+    if let Value::String(val) = val {
+        return Some(val.to_owned());
+    }
+    return logged(val, to_string(val, policy), ValueKind::String, key);
+}
+
+pub(crate) fn element<'de, T>(val: &Value, policy: Coercion, key: &str) -> Result<T, serde_path_to_error::Error<toml::de::Error>>
+where T: serde::Deserialize<'de> {
+    //! Convert an element of a typed array into T. If val doesn't fit, each value policy allows it to become is tried.
+    //! The error is always the one for val itself.
+    let err = match serde_path_to_error::deserialize(val.to_owned()) {
+        Ok(converted) => return Ok(converted),
+        Err(err) => err
+    };
+    let candidates = [
+        to_integer(val, policy).map(Value::Integer),
+        to_float(val, policy).map(Value::Float),
+        to_boolean(val, policy).map(Value::Boolean),
+        to_string(val, policy).map(Value::String),
+    ];
+    for candidate in candidates.into_iter().flatten() {
+        if let Ok(converted) = T::deserialize(candidate.clone()) {
+            logged(val, Some(()), ValueKind::of_toml(&candidate), key);
+            return Ok(converted);
+        }
+    }
+    return Err(err);
+}
+
+fn to_integer(val: &Value, policy: Coercion) -> Option<i64> {
+    return match (val, policy) {
+        (Value::Float(val), Coercion::Lenient) if val.fract() == 0.0 && val.abs() < i64::MAX as f64 => Some(*val as i64),
+        (Value::String(val), Coercion::Lenient) => val.trim().parse().ok(),
+        _ => None
+    };
+}
+
+fn to_float(val: &Value, policy: Coercion) -> Option<f64> {
+    return match (val, policy) {
+        (Value::Integer(val), Coercion::Numeric | Coercion::Lenient) => Some(*val as f64),
+        (Value::String(val), Coercion::Lenient) => val.trim().parse().ok(),
+        _ => None
+    };
+}
+
+fn to_boolean(val: &Value, policy: Coercion) -> Option<bool> {
+    return match (val, policy) {
+        (Value::Integer(1), Coercion::Lenient) => Some(true),
+        (Value::Integer(0), Coercion::Lenient) => Some(false),
+        (Value::String(val), Coercion::Lenient) => match val.trim().to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(true),
+            "false" | "no" | "off" | "0" => Some(false),
+            _ => None
+        },
+        _ => None
+    };
+}
+
+fn to_string(val: &Value, policy: Coercion) -> Option<String> {
+    return match (val, policy) {
+        (Value::Integer(val), Coercion::Lenient) => Some(val.to_string()),
+        (Value::Float(val), Coercion::Lenient) => Some(val.to_string()),
+        (Value::Boolean(val), Coercion::Lenient) => Some(val.to_string()),
+        _ => None
+    };
+}
+
+pub(crate) fn datetime_string(val: &Value, parse_strings: bool, key: &str) -> Option<String> {
+    return datetime(val, parse_strings, key, |val| Some(val.to_string()));
+}
+
+pub(crate) fn date(val: &Value, parse_strings: bool, key: &str) -> Option<NaiveDate> {
+    return datetime(val, parse_strings, key, |val| match val {
+        Datetime { date: Some(date), time: None, offset: None } => naive_date(date),
+        _ => None
+    });
+}
+
+pub(crate) fn time(val: &Value, parse_strings: bool, key: &str) -> Option<NaiveTime> {
+    return datetime(val, parse_strings, key, |val| match val {
+        Datetime { date: None, time: Some(time), offset: None } => naive_time(time),
+        _ => None
+    });
+}
+
+pub(crate) fn naive_datetime(val: &Value, parse_strings: bool, key: &str) -> Option<NaiveDateTime> {
+    return datetime(val, parse_strings, key, |val| match val {
+        Datetime { date: Some(date), time: Some(time), offset: None } => Some(naive_date(date)?.and_time(naive_time(time)?)),
+        _ => None
+    });
+}
+
+pub(crate) fn datetime_tz(val: &Value, parse_strings: bool, key: &str) -> Option<DateTime<FixedOffset>> {
+    return datetime(val, parse_strings, key, |val| {
+        let (date, time, offset) = match val {
+            Datetime { date: Some(date), time: Some(time), offset: Some(offset) } => (date, time, offset),
+            _ => return None
+        };
+        let offset = match offset {
+            Offset::Z => FixedOffset::east_opt(0)?,
+            Offset::Custom { minutes } => FixedOffset::east_opt(i32::from(*minutes) * 60)?,
+        };
+        return naive_date(date)?.and_time(naive_time(time)?).and_local_timezone(offset).single();
+    });
+}
+
+fn datetime<T>(val: &Value, parse_strings: bool, key: &str, convert: fn(&Datetime) -> Option<T>) -> Option<T> {
+    //! Strings are parsed with TOML's datetime syntax, which accepts RFC 3339.
+    let coerced = match val {
+        Value::Datetime(val) => return convert(val),
+        Value::String(val) if parse_strings => val.trim().parse().ok().and_then(|x| convert(&x)),
+        _ => None
+    };
+    return logged(val, coerced, ValueKind::Datetime, key);
+}
+
+fn naive_date(date: &toml::value::Date) -> Option<NaiveDate> {
+    return NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into());
+}
+
+fn naive_time(time: &toml::value::Time) -> Option<NaiveTime> {
+    return NaiveTime::from_hms_nano_opt(time.hour.into(), time.minute.into(), time.second.into(), time.nanosecond);
+}
+
+fn logged<T>(val: &Value, coerced: Option<T>, kind: ValueKind, key: &str) -> Option<T> {
+    if coerced.is_some() {
+        logger::debug(&format!("Coerced '{key}' from {} {val} to {kind}.", ValueKind::of_toml(val)));
+    }
+    return coerced;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coerce_values() {
+        let text = |x: &str| Value::String(x.to_string());
+        assert_eq!(float(&Value::Integer(1), Coercion::Strict, "x"), None);
+        assert_eq!(float(&Value::Integer(1), Coercion::Numeric, "x"), Some(1.0));
+        assert_eq!(float(&text("1.5"), Coercion::Numeric, "x"), None);
+        assert_eq!(float(&text("1.5"), Coercion::Lenient, "x"), Some(1.5));
+
+        assert_eq!(integer(&text("42"), Coercion::Numeric, "x"), None);
+        assert_eq!(integer(&text(" 42 "), Coercion::Lenient, "x"), Some(42));
+        assert_eq!(integer(&Value::Float(2.0), Coercion::Lenient, "x"), Some(2));
+        assert_eq!(integer(&Value::Float(2.5), Coercion::Lenient, "x"), None);
+
+        assert_eq!(boolean(&text("yes"), Coercion::Strict, "x"), None);
+        assert_eq!(boolean(&text("Yes"), Coercion::Lenient, "x"), Some(true));
+        assert_eq!(boolean(&text("off"), Coercion::Lenient, "x"), Some(false));
+        assert_eq!(boolean(&text("maybe"), Coercion::Lenient, "x"), None);
+        assert_eq!(boolean(&Value::Integer(0), Coercion::Lenient, "x"), Some(false));
+
+        assert_eq!(string(&Value::Integer(1), Coercion::Numeric, "x"), None);
+        assert_eq!(string(&Value::Integer(1), Coercion::Lenient, "x"), Some("1".into()));
+        assert_eq!(string(&Value::Array(vec![]), Coercion::Lenient, "x"), None);
+
+        let elements = |x: &[Value], policy| x.iter().map(|x| element::<i64>(x, policy, "x").ok()).collect::<Vec<_>>();
+        assert_eq!(elements(&[Value::Integer(1), text("2"), Value::Float(3.0)], Coercion::Strict), vec![Some(1), None, None]);
+        assert_eq!(elements(&[Value::Integer(1), text("2"), Value::Float(3.0)], Coercion::Lenient), vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(element::<bool>(&text("yes"), Coercion::Lenient, "x").ok(), Some(true));
+        assert_eq!(element::<String>(&Value::Integer(1), Coercion::Lenient, "x").ok(), Some("1".into()));
+        assert!(element::<i64>(&text("x"), Coercion::Lenient, "x").is_err());
+    }
+    #[test]
+    fn coerce_datetimes() {
+        let toml = |x: &str| Value::Datetime(x.parse().unwrap());
+        let text = |x: &str| Value::String(x.to_string());
+        let day = NaiveDate::from_ymd_opt(1979, 5, 27).unwrap();
+        let clock = NaiveTime::from_hms_milli_opt(7, 32, 0, 500).unwrap();
+
+        assert_eq!(date(&toml("1979-05-27"), false, "x"), Some(day));
+        assert_eq!(date(&toml("1979-05-27T07:32:00"), false, "x"), None);
+        assert_eq!(time(&toml("07:32:00.5"), false, "x"), Some(clock));
+        assert_eq!(naive_datetime(&toml("1979-05-27T07:32:00.5"), false, "x"), Some(day.and_time(clock)));
+        assert_eq!(naive_datetime(&toml("1979-05-27T07:32:00Z"), false, "x"), None);
+
+        let tz = datetime_tz(&toml("1979-05-27T07:32:00.5-07:00"), false, "x").unwrap();
+        assert_eq!(tz.naive_local(), day.and_time(clock));
+        assert_eq!(tz.offset().local_minus_utc(), -7 * 3600);
+        assert_eq!(datetime_tz(&toml("1979-05-27T07:32:00Z"), false, "x").unwrap().offset().local_minus_utc(), 0);
+
+        assert_eq!(datetime_tz(&text("1979-05-27T07:32:00.5-07:00"), false, "x"), None);
+        assert_eq!(datetime_tz(&text("1979-05-27T07:32:00.5-07:00"), true, "x"), Some(tz));
+        assert_eq!(date(&text("1979-05-27"), true, "x"), Some(day));
+        assert_eq!(date(&text("May 27th"), true, "x"), None);
+        assert_eq!(datetime_string(&text("1979-05-27 07:32:00Z"), false, "x"), None);
+        assert_eq!(datetime_string(&text("1979-05-27 07:32:00Z"), true, "x"), Some("1979-05-27T07:32:00Z".into()));
+    }
+}
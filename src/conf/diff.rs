@@ -0,0 +1,124 @@
+/*!
+ * Differences between two configs, see MythosConfig::diff(...).
+ * Keys are compared leaf by leaf, so arrays are compared as a whole.
+ *
+ * Field        | Keys that are
+ * added          only in the new config
+ * removed        only in the old config
+ * changed        in both, with different values
+ *
+ * Displayed one key at a time, as "- key = old" and/or "+ key = new".
+ * With the json feature, to_json() returns
+ * {"added": {key: new}, "removed": {key: old}, "changed": {key: {"old": old, "new": new}}}
+ */
+use std::fmt;
+use toml::Table;
+use super::{origin, path, value, ConfigValue};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigDiff {
+    pub added: Vec<(String, ConfigValue)>,
+    pub removed: Vec<(String, ConfigValue)>,
+    /// (key, old, new)
+    pub changed: Vec<(String, ConfigValue, ConfigValue)>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        return self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty();
+    }
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        use serde_json::{json, Map};
+        let added: Map<String, serde_json::Value> = self.added.iter().map(|(key, val)| (key.to_owned(), json_value(val))).collect();
+        let removed: Map<String, serde_json::Value> = self.removed.iter().map(|(key, val)| (key.to_owned(), json_value(val))).collect();
+        let changed: Map<String, serde_json::Value> = self.changed.iter()
+            .map(|(key, old, new)| (key.to_owned(), json!({ "old": json_value(old), "new": json_value(new) })))
+            .collect();
+        return json!({ "added": added, "removed": removed, "changed": changed }).to_string();
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        //! Sorted by key, so a changed key's lines stay together.
+        let mut lines: Vec<(&str, String)> = Vec::new();
+        lines.extend(self.removed.iter().map(|(key, old)| (key.as_str(), format!("- {key} = {old}\n"))));
+        lines.extend(self.changed.iter().map(|(key, old, new)| (key.as_str(), format!("- {key} = {old}\n+ {key} = {new}\n"))));
+        lines.extend(self.added.iter().map(|(key, new)| (key.as_str(), format!("+ {key} = {new}\n"))));
+        lines.sort_by(|a, b| a.0.cmp(b.0));
+        for (_, line) in lines {
+            write!(f, "{line}")?;
+        }
+        return Ok(());
+    }
+}
+
+pub(crate) fn diff(before: &Table, after: &Table) -> ConfigDiff {
+    let leaves = |table: &Table| -> Vec<(String, ConfigValue)> {
+        return origin::leaves(table).into_iter()
+            .filter_map(|key| {
+                let val = value::from_toml(path::lookup(table, &key)?);
+                Some((key, val))
+            })
+            .collect();
+    };
+    let (before, after) = (leaves(before), leaves(after));
+    let mut diff = ConfigDiff::default();
+    for (key, old) in &before {
+        match after.iter().find(|x| x.0 == *key) {
+            Some((_, new)) if new == old => (),
+            Some((_, new)) => diff.changed.push((key.to_owned(), old.to_owned(), new.to_owned())),
+            None => diff.removed.push((key.to_owned(), old.to_owned())),
+        }
+    }
+    for (key, new) in after {
+        if !before.iter().any(|x| x.0 == key) {
+            diff.added.push((key, new));
+        }
+    }
+    return diff;
+}
+
+#[cfg(feature = "json")]
+fn json_value(val: &ConfigValue) -> serde_json::Value {
+    //! Datetimes become strings. NaN and infinite floats become null.
+    use serde_json::Value as Json;
+    return match val {
+        ConfigValue::String(val) | ConfigValue::Datetime(val) => Json::String(val.to_owned()),
+        ConfigValue::Integer(val) => Json::from(*val),
+        ConfigValue::Float(val) => Json::from(*val),
+        ConfigValue::Boolean(val) => Json::Bool(*val),
+        ConfigValue::Array(val) => Json::Array(val.iter().map(json_value).collect()),
+        ConfigValue::Table(val) => Json::Object(val.iter().map(|(key, val)| (key.to_owned(), json_value(val))).collect()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_tables() {
+        let before: Table = toml::from_str("a = 1\nb = 2\n[c]\nd = 3").unwrap();
+        let after: Table = toml::from_str("a = 1\nb = 3\ne = 3").unwrap();
+        let diff = diff(&before, &after);
+        assert_eq!(diff.added, vec![("e".into(), 3.into())]);
+        assert_eq!(diff.removed, vec![("c.d".into(), 3.into())]);
+        assert_eq!(diff.changed, vec![("b".into(), 2.into(), 3.into())]);
+        assert_eq!(diff.to_string(), "- b = 2\n+ b = 3\n- c.d = 3\n+ e = 3\n");
+        assert!(super::diff(&before, &before).is_empty());
+    }
+    #[cfg(feature = "json")]
+    #[test]
+    fn diff_to_json() {
+        let before: Table = toml::from_str("a = [1]\nb = 2").unwrap();
+        let after: Table = toml::from_str("a = [1, 2]\nc = 1979-05-27").unwrap();
+        let json: serde_json::Value = serde_json::from_str(&diff(&before, &after).to_json()).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "added": { "c": "1979-05-27" },
+            "removed": { "b": 2 },
+            "changed": { "a": { "old": [1], "new": [1, 2] } },
+        }));
+    }
+}
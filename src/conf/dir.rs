@@ -0,0 +1,173 @@
+/*!
+ * Abstract (directory) configs.
+ *
+ * Entry          | Key
+ * name.ext         name
+ * name/            name, the whole directory name
+ * .name            skipped
+ *
+ * Entries are sorted by numeric prefix, then by name, e.g. 2-b, 10-a, a, b.
+ * Files are parsed as tables, unless ConfigOptions::raw_extensions or raw_dirs say otherwise.
+ * Raw files become strings, or arrays of bytes if they are not valid UTF-8.
+ * With ConfigOptions::lazy_dirs, files in tables are only parsed once they are accessed.
+ */
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml::Value;
+use crate::{self as mythos_core, printwarn};
+use super::lazy::LazyFile;
+use super::origin::Origins;
+use super::{path, ConfigError, ConfigOptions, MythosConfig, Origin};
+
+pub(crate) struct DirReader<'a> {
+    root: &'a Path,
+    options: &'a ConfigOptions,
+    /// Directories currently being read, used to detect symlink cycles.
+    chain: Vec<PathBuf>,
+}
+
+impl DirReader<'_> {
+    pub fn read(root: &Path, options: &ConfigOptions) -> Result<MythosConfig, ConfigError> {
+        let mut reader = DirReader { root, options, chain: Vec::new() };
+        return reader.read_table(root, false);
+    }
+    fn read_table(&mut self, dir: &Path, raw: bool) -> Result<MythosConfig, ConfigError> {
+        //! Each file is a table and each subdirectory is a table, or an array if options.dir_arrays.
+        self.enter(dir)?;
+        let mut table = MythosConfig { source: Some(dir.to_owned()), ..Default::default() };
+        let mut keys: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+        for entry in entries(dir)? {
+            if let Some(other) = keys.insert(entry.key.to_owned(), entry.path.to_owned()) {
+                return Err(ConfigError::KeyCollision { key: entry.key, paths: vec![other, entry.path] });
+            }
+            let raw = self.is_raw(&entry, raw);
+            if entry.is_dir && self.options.dir_arrays {
+                let key = path::join(&[&entry.key]);
+                let array = self.read_array(&entry.path, raw, &key, &mut table.origins)?;
+                table.origins.insert(key, Origin::File { path: entry.path, line: None });
+                table.table.insert(entry.key, Value::Array(array));
+            } else if entry.is_dir {
+                let sub_table = self.read_table(&entry.path, raw)?;
+                table.extend(&entry.key, sub_table);
+            } else if raw {
+                let val = read_raw(&entry.path)?;
+                table.origins.insert(path::join(&[&entry.key]), Origin::Raw(entry.path));
+                table.table.insert(entry.key, val);
+            } else if self.options.lazy_dirs {
+                let file = LazyFile::new(&entry.path, self.options.interpolate);
+                table.lazy.insert(path::join(&[&entry.key]), file);
+            } else if let Some(file) = read_file(&entry.path) {
+                table.extend(&entry.key, file);
+            }
+        }
+        self.chain.pop();
+        return Ok(table);
+    }
+    fn read_array(&mut self, dir: &Path, raw: bool, key: &str, origins: &mut Origins) -> Result<Vec<Value>, ConfigError> {
+        //! Every entry of dir, in order. Files are tables and subdirectories are arrays.
+        //! Raw elements are added to origins, so they are not interpolated. key is the array's key.
+        self.enter(dir)?;
+        let mut array = Vec::new();
+        for entry in entries(dir)? {
+            let raw = self.is_raw(&entry, raw);
+            let element = format!("{key}[{}]", array.len());
+            if entry.is_dir {
+                array.push(Value::Array(self.read_array(&entry.path, raw, &element, origins)?));
+            } else if raw {
+                array.push(read_raw(&entry.path)?);
+                origins.insert(element, Origin::Raw(entry.path));
+            } else if let Some(file) = read_file(&entry.path) {
+                array.push(Value::Table(file.table));
+            }
+        }
+        self.chain.pop();
+        return Ok(array);
+    }
+    fn enter(&mut self, dir: &Path) -> Result<(), ConfigError> {
+        //! Fails if dir is already being read, i.e. a symlink points back to one of its parents.
+        let canonical = dir.canonicalize().map_err(|err| ConfigError::io(dir, err))?;
+        if self.chain.contains(&canonical) {
+            let mut cycle = self.chain.clone();
+            cycle.push(canonical);
+            return Err(ConfigError::DirCycle(cycle));
+        }
+        self.chain.push(canonical);
+        return Ok(());
+    }
+    fn is_raw(&self, entry: &Entry, parent_raw: bool) -> bool {
+        //! Everything below a raw dir is raw.
+        if parent_raw {
+            return true;
+        }
+        if entry.is_dir {
+            return self.options.raw_dirs.iter().any(|x| self.root.join(x) == entry.path);
+        }
+        let ext = entry.path.extension().and_then(|x| x.to_str()).unwrap_or_default();
+        return self.options.raw_extensions.iter().any(|x| x.trim_start_matches('.') == ext);
+    }
+}
+
+struct Entry {
+    key: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+fn entries(dir: &Path) -> Result<Vec<Entry>, ConfigError> {
+    let mut entries = Vec::new();
+    for item in dir.read_dir().map_err(|err| ConfigError::io(dir, err))? {
+        let path = item.map_err(|err| ConfigError::io(dir, err))?.path();
+        let is_dir = path.is_dir();
+        let key = match (is_dir, path.file_name(), path.file_stem()) {
+            (true, Some(name), _) | (false, _, Some(name)) => name.to_str(),
+            _ => None
+        };
+        match key {
+            Some(key) if key.starts_with('.') => continue,
+            Some(key) => entries.push(Entry { key: key.to_string(), path, is_dir }),
+            None => printwarn!("{path:?} is not a valid key. File was skipped."),
+        }
+    }
+    entries.sort_by_cached_key(|x| sort_key(&x.key));
+    return Ok(entries);
+}
+
+fn read_file(path: &Path) -> Option<MythosConfig> {
+    return match MythosConfig::read_file(path) {
+        Ok(file) => Some(file),
+        Err(err) => {
+            printwarn!("{err}\nTried to read {path:?} as config file. File was skipped.");
+            None
+        }
+    };
+}
+
+fn read_raw(path: &Path) -> Result<Value, ConfigError> {
+    let bytes = std::fs::read(path).map_err(|err| ConfigError::io(path, err))?;
+    return match String::from_utf8(bytes) {
+        Ok(contents) => Ok(Value::String(contents)),
+        Err(err) => Ok(Value::Array(err.into_bytes().into_iter().map(|x| Value::Integer(x.into())).collect())),
+    };
+}
+
+fn sort_key(name: &str) -> (bool, u64, String) {
+    //! Names without a numeric prefix come after those with one.
+    let digits: String = name.chars().take_while(|x| x.is_ascii_digit()).collect();
+    return match digits.parse::<u64>() {
+        Ok(prefix) => (false, prefix, name.to_string()),
+        Err(_) => (true, 0, name.to_string()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_entries() {
+        let mut names = vec!["b", "10-a", "a", "2-b", "02-a"];
+        names.sort_by_cached_key(|x| sort_key(x));
+        assert_eq!(names, vec!["02-a", "2-b", "10-a", "a", "b"]);
+    }
+}
@@ -0,0 +1,180 @@
+/*!
+ * Format-preserving edits for MythosConfig::set_*(...) and MythosConfig::save(...).
+ * Edits are applied to both the config's table and a toml_edit::Document.
+ * The document is what gets written, so comments and key order in the user's file survive.
+ */
+use std::path::{Path, PathBuf};
+use toml::Value;
+use toml_edit::{Decor, Document, Item};
+use crate::dirs;
+use super::ConfigError;
+use super::path::Segment;
+
+/// What the document is built from the first time the config is edited.
+#[derive(Debug, Default, Clone)]
+pub(crate) enum Seed {
+    /// Serialize the config's current values. Only used until something is added that shouldn't be saved,
+    /// see MythosConfig::freeze_seed(...).
+    #[default]
+    Current,
+    /// Serialize these values, e.g. an INI file as it was read, without defaults or expanded references.
+    Table(toml::Table),
+    /// Only contain the edits, e.g. a local layer that does not exist yet.
+    Empty,
+    /// Parse this file.
+    File(PathBuf),
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Editor {
+    /// Used to find the LocalConfig file for save(...).
+    pub util_name: Option<String>,
+    pub seed: Seed,
+    doc: Option<Document>,
+}
+
+impl Editor {
+    pub fn new(util_name: Option<&str>, seed: Seed) -> Editor {
+        return Editor { util_name: util_name.map(|x| x.to_string()), seed, doc: None };
+    }
+    pub fn document(&mut self, current: &toml::Table) -> Result<&mut Document, ConfigError> {
+        //! Get the document, building it from seed if needed.
+        if self.doc.is_none() {
+            let doc = match &self.seed {
+                Seed::Empty => Document::new(),
+                Seed::Current => to_document(current)?,
+                Seed::Table(table) => to_document(table)?,
+                Seed::File(path) => {
+                    let contents = std::fs::read_to_string(path).map_err(|err| ConfigError::io(path, err))?;
+                    contents.parse::<Document>()
+                        .map_err(|err| ConfigError::parse(path, &contents, err.message(), err.span().map(|x| x.start)))?
+                }
+            };
+            self.doc = Some(doc);
+        }
+        return Ok(self.doc.as_mut().unwrap());
+    }
+    pub fn is_edited(&self) -> bool {
+        //! Whether the document was built, i.e. the config was edited since it was read.
+        return self.doc.is_some();
+    }
+    pub fn target(&self) -> Result<PathBuf, ConfigError> {
+        //! Where save(...) writes. This is always inside $MYTHOS_LOCAL_CONFIG_DIR.
+        let local_dir = dirs::expand_path(dirs::MythosDir::LocalConfig, "");
+        if let Seed::File(path) = &self.seed {
+            if path.starts_with(&local_dir) {
+                return Ok(path.to_owned());
+            }
+        }
+        let util_name = self.util_name.as_ref().ok_or(ConfigError::NoSaveTarget)?;
+        let local = dirs::expand_path(dirs::MythosDir::LocalConfig, util_name);
+        let target = local.with_extension("toml");
+        // A local file that is probed before util.toml, e.g. util.ini, would hide the saved file.
+        return match super::clean_and_validate(local, false) {
+            Ok(path) if path == target => Ok(target),
+            Err(ConfigError::NotFound { .. }) => Ok(target),
+            _ => Err(ConfigError::NoSaveTarget)
+        };
+    }
+}
+
+fn to_document(table: &toml::Table) -> Result<Document, ConfigError> {
+    return toml::to_string(table)
+        .map_err(|err| ConfigError::Serialize(err.to_string()))?
+        .parse::<Document>()
+        .map_err(|err| ConfigError::Serialize(err.to_string()));
+}
+
+pub(crate) fn set(doc: &mut Document, segments: &[Segment], value: &Value) -> bool {
+    //! Set the value at segments, creating tables as needed.
+    //! Returns false if a parent is not a table.
+    let (last, parents) = match segments.split_last() {
+        Some((Segment::Key(last), parents)) => (last, parents),
+        _ => return false
+    };
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    for segment in parents {
+        let key = match segment {
+            Segment::Key(key) => key,
+            Segment::Index(_) => return false
+        };
+        table = match table.entry(key).or_insert(Item::Table(toml_edit::Table::new())).as_table_like_mut() {
+            Some(table) => table,
+            None => return false
+        };
+    }
+
+    let mut new_val = to_edit_value(value);
+    match table.get_mut(last) {
+        // Keep comments/whitespace around the old value.
+        Some(Item::Value(old)) => {
+            *new_val.decor_mut() = old.decor().clone();
+            *old = new_val;
+        },
+        _ => {
+            table.insert(last, Item::Value(new_val));
+        }
+    }
+    return true;
+}
+
+pub(crate) fn remove(doc: &mut Document, segments: &[Segment]) -> Option<(Item, Decor)> {
+    //! Returns the removed item and the comments around its key.
+    let (last, parents) = match segments.split_last() {
+        Some((Segment::Key(last), parents)) => (last, parents),
+        _ => return None
+    };
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    for segment in parents {
+        table = match segment {
+            Segment::Key(key) => table.get_mut(key).and_then(|x| x.as_table_like_mut())?,
+            Segment::Index(_) => return None
+        };
+    }
+    let decor = table.key_decor(last).cloned().unwrap_or_default();
+    return Some((table.remove(last)?, decor));
+}
+
+pub(crate) fn insert(doc: &mut Document, segments: &[Segment], (item, decor): (Item, Decor)) -> bool {
+    //! Insert an item returned by remove(...), creating tables as needed.
+    //! Returns false if a parent is not a table.
+    let (last, parents) = match segments.split_last() {
+        Some((Segment::Key(last), parents)) => (last, parents),
+        _ => return false
+    };
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    for segment in parents {
+        let key = match segment {
+            Segment::Key(key) => key,
+            Segment::Index(_) => return false
+        };
+        table = match table.entry(key).or_insert(Item::Table(toml_edit::Table::new())).as_table_like_mut() {
+            Some(table) => table,
+            None => return false
+        };
+    }
+    table.insert(last, item);
+    if let Some(key_decor) = table.key_decor_mut(last) {
+        *key_decor = decor;
+    }
+    return true;
+}
+
+pub(crate) fn write(doc: &Document, path: &Path) -> Result<(), ConfigError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| ConfigError::io(parent, err))?;
+    }
+    return std::fs::write(path, doc.to_string()).map_err(|err| ConfigError::io(path, err));
+}
+
+fn to_edit_value(value: &Value) -> toml_edit::Value {
+    return match value {
+        Value::String(val) => val.into(),
+        Value::Integer(val) => (*val).into(),
+        Value::Float(val) => (*val).into(),
+        Value::Boolean(val) => (*val).into(),
+        Value::Datetime(val) => (*val).into(),
+        Value::Array(val) => val.iter().map(to_edit_value).collect::<toml_edit::Array>().into(),
+        Value::Table(val) => val.iter().map(|(k, v)| (k, to_edit_value(v))).collect::<toml_edit::InlineTable>().into(),
+    };
+}
@@ -0,0 +1,109 @@
+/*!
+ * Environment variable overrides.
+ * MYTHOS_<UTIL>__<SECTION>__<KEY>=value sets util's section.key to value.
+ * e.g. MYTHOS_ARACHNE__SEARCH__DEPTH=3
+ *
+ * Values are parsed as TOML literals (3, true, [1, 2], "quoted"). 
+ * Anything else is treated as a plain string.
+ */
+use std::collections::BTreeMap;
+use toml::{Table, Value};
+use crate::{self as mythos_core, printwarn};
+use super::path;
+
+pub(crate) fn util_var(util_name: &str) -> String {
+    //! Start of every variable for util_name, also used by $MYTHOS_<UTIL>_PROFILE.
+    //! Non-alphanumeric chars in util_name become '_', e.g. "mythos-conf" -> "MYTHOS_MYTHOS_CONF"
+    let util: String = util_name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    return format!("MYTHOS_{util}");
+}
+
+pub(crate) fn prefix(util_name: &str) -> String {
+    return format!("{}__", util_var(util_name));
+}
+
+pub(crate) fn apply_overrides(table: &mut Table, util_name: &str) -> BTreeMap<String, String> {
+    //! Apply every override for util_name to table.
+    //! Returns a map of key path -> name of the variable that set it.
+    let prefix = prefix(util_name);
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(var, _)| var.starts_with(&prefix))
+        .collect();
+    vars.sort();
+
+    let mut sources = BTreeMap::new();
+    for (var, raw) in vars {
+        let segments: Vec<&str> = var[prefix.len()..].split("__").collect();
+        if segments.iter().any(|x| x.is_empty()) {
+            printwarn!("Ignoring ${var}: variable name contains an empty key.");
+            continue;
+        }
+        match set_override(table, &segments, parse_value(&raw)) {
+            Some(key) => {
+                sources.insert(key, var);
+            },
+            None => printwarn!("Ignoring ${var}: one of its sections is not a table.")
+        }
+    }
+    return sources;
+}
+
+fn set_override(mut table: &mut Table, segments: &[&str], value: Value) -> Option<String> {
+    let (last, sections) = segments.split_last()?;
+    let mut keys = Vec::new();
+    for segment in sections {
+        let key = match_key(table, segment);
+        keys.push(key.clone());
+        table = match table.entry(key).or_insert(Value::Table(Table::new())) {
+            Value::Table(table) => table,
+            _ => return None
+        };
+    }
+    let key = match_key(table, last);
+    keys.push(key.clone());
+    table.insert(key, value);
+    return Some(path::join(&keys));
+}
+
+fn match_key(table: &Table, segment: &str) -> String {
+    // Env vars are usually uppercase, while config keys are usually lowercase.
+    return table.keys()
+        .find(|key| key.eq_ignore_ascii_case(segment))
+        .cloned()
+        .unwrap_or(segment.to_lowercase());
+}
+
+fn parse_value(raw: &str) -> Value {
+    //! raw must be exactly one TOML value, otherwise e.g. "1\nother = 2" would silently become 1.
+    return match toml::from_str::<Table>(&format!("value = {raw}")) {
+        Ok(mut table) if table.len() == 1 => table.remove("value").unwrap_or(Value::String(raw.to_string())),
+        _ => Value::String(raw.to_string())
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(prefix("mythos-conf"), "MYTHOS_MYTHOS_CONF__");
+        assert_eq!(parse_value("3"), Value::Integer(3));
+        assert_eq!(parse_value("[1, 2]"), Value::Array(vec![1.into(), 2.into()]));
+        assert_eq!(parse_value("\"quoted\""), Value::String("quoted".into()));
+        assert_eq!(parse_value("~/notes dir"), Value::String("~/notes dir".into()));
+        assert_eq!(parse_value("1\nother = 2"), Value::String("1\nother = 2".into()));
+        assert_eq!(parse_value("1\n[table]"), Value::String("1\n[table]".into()));
+    }
+    #[test]
+    fn override_keys() {
+        let mut table: Table = toml::from_str("[Search]\ndepth = 1\nname = 'x'").unwrap();
+        assert_eq!(set_override(&mut table, &["SEARCH", "DEPTH"], 3.into()), Some("Search.depth".into()));
+        assert_eq!(set_override(&mut table, &["NEW", "KEY"], 4.into()), Some("new.key".into()));
+        assert_eq!(set_override(&mut table, &["SEARCH", "NAME", "X"], 5.into()), None);
+        assert_eq!(table["Search"]["depth"], Value::Integer(3));
+        assert_eq!(table["new"]["key"], Value::Integer(4));
+    }
+}
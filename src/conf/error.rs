@@ -0,0 +1,147 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Reasons a config could not be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// No file or directory matched. Contains every path that was probed, in order.
+    NotFound { path_snippet: String, tried: Vec<PathBuf> },
+    /// The file exists, but could not be read.
+    Io { path: PathBuf, source: std::io::Error },
+    /// The file was read, but is not valid. line and column are 1-indexed.
+    /// snippet is the offending line of the file.
+    Parse { path: PathBuf, line: usize, column: usize, message: String, snippet: String },
+    /// A file was required, but a directory without a 'config' file was found.
+    IsDirectory(PathBuf),
+    /// The config could not be converted into the requested type.
+    /// key is a dotted path to the value that failed, or empty if the failure was at the root.
+    Deserialize { key: String, message: String },
+    /// A value was required, but key is not set.
+    MissingKey(String),
+    /// The value at key exists, but could not be read as the requested kind, e.g. a malformed duration.
+    InvalidValue { key: String, message: String },
+    /// key is not a valid path, or one of its parents is not a table.
+    InvalidKey(String),
+    /// The config could not be converted into TOML.
+    Serialize(String),
+    /// The include key is not a path or list of paths.
+    InvalidInclude { path: PathBuf, message: String },
+    /// A file includes itself. The chain starts with the file that was opened and ends with the repeated file.
+    IncludeCycle(Vec<PathBuf>),
+    /// An included file could not be read. The chain starts with the file that was opened and ends with the file that failed.
+    Include { chain: Vec<PathBuf>, source: Box<ConfigError> },
+    /// save() was called, but there is no LocalConfig file to write to, e.g. the config was read from a directory,
+    /// or the util's local config is not TOML, so a saved util.toml would never be read.
+    NoSaveTarget,
+    /// A ${...} reference in key could not be expanded.
+    Interpolation { key: String, message: String },
+    /// A directory config links back to one of its parents. The chain ends with the repeated directory.
+    DirCycle(Vec<PathBuf>),
+    /// Two entries of a directory config map to the same key, e.g. 'foo.toml' and 'foo/'.
+    KeyCollision { key: String, paths: Vec<PathBuf> },
+    /// The selected profile, or one it inherits from, is missing or malformed.
+    InvalidProfile { name: String, message: String },
+    /// Profiles inherit from each other. The chain starts with the selected profile and ends with the repeated one.
+    ProfileCycle(Vec<String>),
+    /// A migration step could not be applied, or the upgraded config could not be written.
+    Migration(String),
+}
+
+impl ConfigError {
+    pub(crate) fn io(path: &std::path::Path, source: std::io::Error) -> ConfigError {
+        return ConfigError::Io { path: path.to_owned(), source };
+    }
+    pub(crate) fn parse(path: &std::path::Path, contents: &str, message: &str, offset: Option<usize>) -> ConfigError {
+        //! Locate offset (a byte index into contents) and build a Parse error.
+        //! If offset is unknown, the error points to the start of the file.
+        let offset = offset.unwrap_or(0).min(contents.len());
+        let line_start = contents[..offset].rfind('\n').map(|x| x + 1).unwrap_or(0);
+        let line_end = contents[offset..].find('\n').map(|x| x + offset).unwrap_or(contents.len());
+
+        return ConfigError::Parse {
+            path: path.to_owned(),
+            line: contents[..offset].matches('\n').count() + 1,
+            column: contents[line_start..offset].chars().count() + 1,
+            message: message.trim().to_string(),
+            snippet: contents[line_start..line_end].trim_end_matches('\r').to_string(),
+        };
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ConfigError::NotFound { path_snippet, tried } => {
+                write!(f, "Could not find a config file for '{path_snippet}'. Tried:")?;
+                for path in tried {
+                    write!(f, "\n    {}", path.display())?;
+                }
+                Ok(())
+            },
+            ConfigError::Io { path, source } => {
+                write!(f, "Could not read config file {}. Error msg: {source}", path.display())
+            },
+            ConfigError::Parse { path, line, column, message, snippet } => {
+                // Mimic rustc's diagnostics:
+                //   --> path:line:column
+                //    |
+                //  3 | key = = 1
+                //    |       ^
+                let gutter = " ".repeat(line.to_string().len());
+                writeln!(f, "Could not parse config file: {message}")?;
+                writeln!(f, "{gutter}--> {}:{line}:{column}", path.display())?;
+                writeln!(f, "{gutter} |")?;
+                writeln!(f, "{line} | {snippet}")?;
+                write!(f, "{gutter} | {}^", " ".repeat(column.saturating_sub(1)))
+            },
+            ConfigError::IsDirectory(path) => {
+                write!(f, "'{}' is a directory and does not contain a 'config' file", path.display())
+            },
+            ConfigError::Deserialize { key, message } if key.is_empty() => {
+                write!(f, "Could not convert config: {message}")
+            },
+            ConfigError::Deserialize { key, message } => {
+                write!(f, "Could not convert config key '{key}': {message}")
+            },
+            ConfigError::MissingKey(key) => write!(f, "'{key}' is not set"),
+            ConfigError::InvalidValue { key, message } => write!(f, "Invalid value for '{key}': {message}"),
+            ConfigError::InvalidKey(key) => write!(f, "Cannot set '{key}'. Key is malformed or one of its parents is not a table"),
+            ConfigError::Serialize(message) => write!(f, "Could not write config: {message}"),
+            ConfigError::InvalidInclude { path, message } => {
+                write!(f, "Invalid include in {}: {message}", path.display())
+            },
+            ConfigError::IncludeCycle(chain) => {
+                write!(f, "Config files include each other: {}", display_chain(chain))
+            },
+            ConfigError::Include { chain, source } => {
+                write!(f, "{source}\nIncluded by: {}", display_chain(chain))
+            },
+            ConfigError::NoSaveTarget => write!(f, "Config has no local config file to save to. Use save_to(...) instead"),
+            ConfigError::Interpolation { key, message } => write!(f, "Could not expand '{key}': {message}"),
+            ConfigError::DirCycle(chain) => {
+                write!(f, "Config directory links back to itself: {}", display_chain(chain))
+            },
+            ConfigError::KeyCollision { key, paths } => {
+                let paths: Vec<String> = paths.iter().map(|x| x.display().to_string()).collect();
+                write!(f, "'{key}' is defined more than once: {}", paths.join(", "))
+            },
+            ConfigError::InvalidProfile { name, message } => write!(f, "Invalid profile '{name}': {message}"),
+            ConfigError::Migration(message) => write!(f, "Could not migrate config: {message}"),
+            ConfigError::ProfileCycle(chain) => write!(f, "Profiles inherit from each other: {}", chain.join(" -> ")),
+        };
+    }
+}
+
+fn display_chain(chain: &[PathBuf]) -> String {
+    return chain.iter().map(|x| x.display().to_string()).collect::<Vec<String>>().join(" -> ");
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Include { source, .. } => Some(source.as_ref()),
+            _ => None
+        };
+    }
+}
@@ -0,0 +1,405 @@
+/*!
+ * Parsers for the file formats a config can be written in.
+ * The format is chosen by extension:
+ *
+ * Extension      | Format
+ * .toml, none      TOML
+ * .ini             INI
+ * .conf            TOML, or INI/shell-style KEY=value if the file is not valid TOML
+ * .json            JSON, requires the json feature
+ * .yaml, .yml      YAML, requires the yaml feature
+ *
+ * Every format produces the same tree: INI sections become subsections.
+ * INI values are not typed, they are always strings.
+ * JSON/YAML nulls have no TOML equivalent, so they are dropped.
+ */
+use std::collections::BTreeMap;
+use std::path::Path;
+use toml::{Table, Value};
+use super::{path, ConfigError};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum ConfigFormat {
+    Toml,
+    Ini,
+    Conf,
+    #[cfg(feature = "json")]
+    Json,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> ConfigFormat {
+        return match path.extension().and_then(|x| x.to_str()) {
+            Some("ini") => ConfigFormat::Ini,
+            Some("conf") => ConfigFormat::Conf,
+            #[cfg(feature = "json")]
+            Some("json") => ConfigFormat::Json,
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Toml,
+        };
+    }
+}
+
+pub(crate) fn parse(path: &Path, contents: &str) -> Result<(Table, ConfigFormat), ConfigError> {
+    //! Returns the parsed table and the format it was actually written in, which is never Conf.
+    let format = ConfigFormat::from_path(path);
+    let toml_err = match format {
+        #[cfg(feature = "json")]
+        ConfigFormat::Json => return parse_json(path, contents).map(|table| (table, format)),
+        #[cfg(feature = "yaml")]
+        ConfigFormat::Yaml => return parse_yaml(path, contents).map(|table| (table, format)),
+        ConfigFormat::Ini => None,
+        _ => match toml::from_str::<Table>(contents) {
+            Ok(table) => return Ok((table, ConfigFormat::Toml)),
+            Err(err) => Some(err)
+        }
+    };
+    let ini = parse_ini(contents).map(|table| (table, ConfigFormat::Ini));
+
+    return match (format, toml_err) {
+        (ConfigFormat::Toml, Some(err)) => Err(ConfigError::parse(path, contents, err.message(), err.span().map(|x| x.start))),
+        // A .conf file that is neither TOML or INI was most likely meant to be TOML.
+        (ConfigFormat::Conf, Some(err)) => ini.or(Err(
+            ConfigError::parse(path, contents, err.message(), err.span().map(|x| x.start))
+        )),
+        _ => ini.map_err(|(msg, offset)| ConfigError::parse(path, contents, &msg, Some(offset))),
+    };
+}
+
+pub(crate) fn is_toml(path: &Path) -> bool {
+    //! Whether the file at path is written in TOML, e.g. a .conf file that is not INI.
+    return std::fs::read_to_string(path).is_ok_and(|contents| matches!(parse(path, &contents), Ok((_, ConfigFormat::Toml))));
+}
+
+pub(crate) fn lines(contents: &str, format: ConfigFormat) -> BTreeMap<String, usize> {
+    //! The 1-indexed line each key is set on, by normalized path.
+    //! Only TOML and INI files are tracked.
+    return match format {
+        ConfigFormat::Toml => toml_lines(contents),
+        ConfigFormat::Ini => parse_ini_lines(contents).map(|x| x.1).unwrap_or_default(),
+        _ => BTreeMap::new(),
+    };
+}
+
+fn toml_lines(contents: &str) -> BTreeMap<String, usize> {
+    //! Scan for 'key = ' and '[[table]]' lines. contents is assumed to be valid TOML.
+    //! Multi-line strings and arrays are skipped, so their contents can't be mistaken for keys or headers.
+    let mut lines = BTreeMap::new();
+    let mut table: Vec<String> = Vec::new();
+    let mut multi_line: Option<&str> = None;
+    let mut depth = 0;
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if let Some(delim) = multi_line {
+            if line.matches(delim).count() % 2 == 1 {
+                multi_line = None;
+            }
+            continue;
+        }
+        if depth > 0 {
+            depth = (depth + bracket_depth(line)).max(0);
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix("[[") {
+            table = toml_key(header, ']').map(|x| x.0).unwrap_or_default();
+            lines.entry(path::join(&table)).or_insert(i + 1);
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            table = toml_key(header, ']').map(|x| x.0).unwrap_or_default();
+            continue;
+        }
+        let (key, val) = match toml_key(line, '=') {
+            Some(key) => key,
+            None => continue
+        };
+        lines.insert(path::join(&[table.clone(), key].concat()), i + 1);
+        multi_line = ["\"\"\"", "'''"].into_iter().find(|x| val.matches(x).count() % 2 == 1);
+        depth = bracket_depth(val).max(0);
+    }
+    return lines;
+}
+
+fn bracket_depth(val: &str) -> isize {
+    //! How many more brackets val opens than it closes, ignoring strings and comments.
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in val.chars() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            },
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => break,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => ()
+        }
+        escaped = false;
+    }
+    return depth;
+}
+
+fn toml_key(line: &str, end: char) -> Option<(Vec<String>, &str)> {
+    //! Split a dotted key that ends at end, e.g. 'a."b.c" = 1' -> (["a", "b.c"], " 1").
+    let mut keys = vec![String::new()];
+    let mut quote: Option<char> = None;
+    let mut bare = true;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => keys.last_mut()?.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                bare = false;
+            },
+            (None, c) if c == '.' || c == end => {
+                if bare && keys.last()?.is_empty() {
+                    return None;
+                }
+                if c == end {
+                    return Some((keys, &line[i + 1..]));
+                }
+                keys.push(String::new());
+                bare = true;
+            },
+            (None, c) if c.is_whitespace() => (),
+            (None, c) if c.is_ascii_alphanumeric() || c == '-' || c == '_' => keys.last_mut()?.push(c),
+            _ => return None
+        }
+    }
+    return None;
+}
+
+fn parse_ini(contents: &str) -> Result<Table, (String, usize)> {
+    return parse_ini_lines(contents).map(|x| x.0);
+}
+
+fn parse_ini_lines(contents: &str) -> Result<(Table, BTreeMap<String, usize>), (String, usize)> {
+    //! Parse INI or shell-style KEY=value files. Also returns the line each key is set on.
+    //! On failure, returns an error message and the byte offset of the offending line.
+    let mut table = Table::new();
+    let mut lines = BTreeMap::new();
+    let mut section: Vec<String> = Vec::new();
+    let mut offset = 0;
+
+    for (i, raw_line) in contents.split_inclusive('\n').enumerate() {
+        let line_offset = offset;
+        offset += raw_line.len();
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            let name = line.strip_prefix('[')
+                .and_then(|x| x.strip_suffix(']'))
+                .ok_or((format!("Unclosed section header '{line}'"), line_offset))?;
+            section = name.split('.').map(|x| x.trim().to_string()).collect();
+            if section.iter().any(|x| x.is_empty()) {
+                return Err((format!("Invalid section name '{name}'"), line_offset));
+            }
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, val) = match line.find(['=', ':']) {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err((format!("Expected 'key = value', found '{line}'"), line_offset))
+        };
+        if key.is_empty() {
+            return Err(("Missing key before '='".to_string(), line_offset));
+        }
+
+        let mut parent = &mut table;
+        for name in &section {
+            parent = match parent.entry(name).or_insert(Value::Table(Table::new())) {
+                Value::Table(table) => table,
+                _ => return Err((format!("Section '{name}' is already a key"), line_offset))
+            };
+        }
+        parent.insert(key.to_string(), Value::String(parse_ini_value(val)));
+        lines.insert(path::join(&[section.clone(), vec![key.to_string()]].concat()), i + 1);
+    }
+    return Ok((table, lines));
+}
+
+fn parse_ini_value(val: &str) -> String {
+    //! Strip quotes, or an inline comment if unquoted.
+    for quote in ['"', '\''] {
+        if let Some(inner) = val.strip_prefix(quote) {
+            if let Some(end) = inner.rfind(quote) {
+                let inner = &inner[..end];
+                return if quote == '"' { inner.replace("\\\"", "\"") } else { inner.to_string() };
+            }
+        }
+    }
+    // Comments must be preceded by whitespace, e.g. url = http://a.b/#anchor keeps its '#'.
+    let end = [" ;", "\t;", " #", "\t#"].iter()
+        .filter_map(|x| val.find(x))
+        .min()
+        .unwrap_or(val.len());
+    return val[..end].trim().to_string();
+}
+
+#[cfg(feature = "json")]
+fn parse_json(path: &Path, contents: &str) -> Result<Table, ConfigError> {
+    use serde_json::Value as Json;
+    fn convert(val: Json) -> Option<Value> {
+        return match val {
+            Json::Null => None,
+            Json::Bool(val) => Some(Value::Boolean(val)),
+            Json::Number(val) => match val.as_i64() {
+                Some(val) => Some(Value::Integer(val)),
+                None => val.as_f64().map(Value::Float)
+            },
+            Json::String(val) => Some(Value::String(val)),
+            Json::Array(val) => Some(Value::Array(val.into_iter().filter_map(convert).collect())),
+            Json::Object(val) => Some(Value::Table(
+                val.into_iter().filter_map(|(k, v)| Some((k, convert(v)?))).collect()
+            )),
+        };
+    }
+
+    return match serde_json::from_str::<Json>(contents) {
+        Ok(val @ Json::Object(_)) => match convert(val) {
+            Some(Value::Table(table)) => Ok(table),
+            _ => unreachable!("Objects are always converted into tables")
+        },
+        Ok(_) => Err(ConfigError::parse(path, contents, "Expected a JSON object", None)),
+        Err(err) => {
+            let msg = err.to_string();
+            Err(ConfigError::parse(path, contents, &msg, line_offset(contents, err.line(), err.column())))
+        }
+    };
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(path: &Path, contents: &str) -> Result<Table, ConfigError> {
+    use serde_yaml::Value as Yaml;
+    fn convert(val: Yaml) -> Option<Value> {
+        return match val {
+            Yaml::Null => None,
+            Yaml::Bool(val) => Some(Value::Boolean(val)),
+            Yaml::Number(val) => match val.as_i64() {
+                Some(val) => Some(Value::Integer(val)),
+                None => val.as_f64().map(Value::Float)
+            },
+            Yaml::String(val) => Some(Value::String(val)),
+            Yaml::Sequence(val) => Some(Value::Array(val.into_iter().filter_map(convert).collect())),
+            Yaml::Mapping(val) => Some(Value::Table(
+                val.into_iter().filter_map(|(k, v)| Some((key_to_string(k)?, convert(v)?))).collect()
+            )),
+            Yaml::Tagged(val) => convert(val.value),
+        };
+    }
+    fn key_to_string(key: Yaml) -> Option<String> {
+        // TOML keys must be strings, while YAML keys can be any scalar.
+        return match key {
+            Yaml::String(key) => Some(key),
+            Yaml::Bool(key) => Some(key.to_string()),
+            Yaml::Number(key) => Some(key.to_string()),
+            _ => None
+        };
+    }
+
+    return match serde_yaml::from_str::<Yaml>(contents) {
+        // An empty file is an empty config.
+        Ok(Yaml::Null) => Ok(Table::new()),
+        Ok(val @ Yaml::Mapping(_)) => match convert(val) {
+            Some(Value::Table(table)) => Ok(table),
+            _ => unreachable!("Mappings are always converted into tables")
+        },
+        Ok(_) => Err(ConfigError::parse(path, contents, "Expected a YAML mapping", None)),
+        Err(err) => {
+            let msg = err.to_string();
+            Err(ConfigError::parse(path, contents, &msg, err.location().map(|x| x.index())))
+        }
+    };
+}
+
+#[cfg(feature = "json")]
+fn line_offset(contents: &str, line: usize, column: usize) -> Option<usize> {
+    //! Convert a 1-indexed line and column into a byte offset.
+    let line_start: usize = contents.split_inclusive('\n').take(line.saturating_sub(1)).map(|x| x.len()).sum();
+    return Some((line_start + column.saturating_sub(1)).min(contents.len()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ini_file() {
+        let table = parse_ini("; comment\ntop = level\n[a]\nname = John Doe ; comment\nquoted = \"x ; y\"\npath: /bin\n[a.b]\nvalue = 42").unwrap();
+        assert_eq!(table["top"], Value::String("level".into()));
+        assert_eq!(table["a"]["name"], Value::String("John Doe".into()));
+        assert_eq!(table["a"]["quoted"], Value::String("x ; y".into()));
+        assert_eq!(table["a"]["path"], Value::String("/bin".into()));
+        assert_eq!(table["a"]["b"]["value"], Value::String("42".into()));
+        assert_eq!(parse_ini("a = 1\nno value"), Err(("Expected 'key = value', found 'no value'".into(), 6)));
+        assert!(parse_ini("[a\nb = 1").is_err());
+    }
+    #[test]
+    fn parse_shell_file() {
+        let table = parse_ini("#!/bin/bash\nexport DIR=\"$HOME/.config\"\nNAME='single'\nURL=http://a.b/#anchor").unwrap();
+        assert_eq!(table["DIR"], Value::String("$HOME/.config".into()));
+        assert_eq!(table["NAME"], Value::String("single".into()));
+        assert_eq!(table["URL"], Value::String("http://a.b/#anchor".into()));
+    }
+    #[test]
+    fn find_lines() {
+        let toml = "a = 1\nb.'c.d' = \"\"\"\nx = 1\n\"\"\"\n[t]\n\"e\" = [\n  2,\n]\n[[arr]]\nf = 1\n[[arr]]";
+        let found = lines(toml, ConfigFormat::Toml);
+        assert_eq!(found.get("a"), Some(&1));
+        assert_eq!(found.get("b.\"c.d\""), Some(&2));
+        assert_eq!(found.get("x"), None);
+        assert_eq!(found.get("t.e"), Some(&6));
+        assert_eq!(found.get("arr"), Some(&9));
+
+        // Nested arrays are not table headers.
+        let toml = "[t]\nnested = [\n  [1, 2],\n  [\"]\", '[#'], # ]\n]\nx = 1";
+        let found = lines(toml, ConfigFormat::Toml);
+        assert_eq!(found.get("t.nested"), Some(&2));
+        assert_eq!(found.get("t.x"), Some(&6));
+        assert_eq!(found.len(), 2);
+
+        let found = lines("top = 1\n\n[a.b]\nkey = 2", ConfigFormat::Ini);
+        assert_eq!(found.get("a.b.key"), Some(&4));
+    }
+    #[cfg(feature = "json")]
+    #[test]
+    fn parse_json_file() {
+        let path = Path::new("test.json");
+        let (table, _) = parse(path, r#"{"a": 1, "b": null, "c": [1.5, null], "d": {"e": "f"}}"#).unwrap();
+        assert_eq!(table["a"], Value::Integer(1));
+        assert!(!table.contains_key("b"));
+        assert_eq!(table["c"], Value::Array(vec![Value::Float(1.5)]));
+        assert_eq!(table["d"]["e"], Value::String("f".into()));
+
+        match parse(path, "{\n  \"a\": }") {
+            Err(ConfigError::Parse { line, snippet, .. }) => assert_eq!((line, snippet.as_str()), (2, "  \"a\": }")),
+            res => panic!("Expected Parse error, got {res:?}")
+        }
+    }
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn parse_yaml_file() {
+        let path = Path::new("test.yml");
+        let (table, _) = parse(path, "a: 1\nb: ~\n1: one\nd:\n  e: [x, y]\n").unwrap();
+        assert_eq!(table["a"], Value::Integer(1));
+        assert!(!table.contains_key("b"));
+        assert_eq!(table["1"], Value::String("one".into()));
+        assert_eq!(table["d"]["e"], Value::Array(vec!["x".into(), "y".into()]));
+        assert!(parse(path, "- a\n- b").is_err());
+    }
+}
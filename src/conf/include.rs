@@ -0,0 +1,64 @@
+/*!
+ * include = "file" or include = ["file", "~/shared/keys-*.toml"]
+ * Included files are merged in order, then the including file is merged on top of them.
+ * Paths are relative to the including file. '~' and $HOME are expanded and globs are allowed.
+ */
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use crate::dirs;
+use super::ConfigError;
+
+pub(crate) const INCLUDE_KEY: &str = "include";
+
+pub(crate) fn take_includes(table: &mut Table, path: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+    //! Remove the include key from table and resolve its paths.
+    let entries = match table.remove(INCLUDE_KEY) {
+        None => return Ok(Vec::new()),
+        Some(Value::String(entry)) => vec![entry],
+        Some(Value::Array(entries)) => entries.into_iter()
+            .map(|x| match x {
+                Value::String(entry) => Ok(entry),
+                x => Err(invalid(path, &format!("expected a path, found {x}")))
+            })
+            .collect::<Result<Vec<String>, ConfigError>>()?,
+        Some(x) => return Err(invalid(path, &format!("expected a path or list of paths, found {x}")))
+    };
+
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let mut paths = Vec::new();
+    for entry in entries {
+        let resolved = parent.join(expand_home(&entry));
+        if !entry.contains(['*', '?', '[']) {
+            if !resolved.exists() {
+                return Err(ConfigError::NotFound { path_snippet: entry, tried: vec![resolved] });
+            }
+            paths.push(resolved);
+            continue;
+        }
+
+        let pattern = resolved.to_string_lossy().to_string();
+        let mut matches: Vec<PathBuf> = glob::glob(&pattern)
+            .map_err(|err| invalid(path, &format!("'{entry}' is not a valid pattern. {err}")))?
+            .filter_map(|x| x.ok())
+            .filter(|x| x.is_file())
+            .collect();
+        matches.sort();
+        paths.extend(matches);
+    }
+    return Ok(paths);
+}
+
+fn expand_home(entry: &str) -> String {
+    let home = match dirs::get_home() {
+        Some(home) => home.to_string_lossy().to_string(),
+        None => return entry.to_string()
+    };
+    if let Some(rest) = entry.strip_prefix('~') {
+        return format!("{home}{rest}");
+    }
+    return entry.replace("$HOME", &home);
+}
+
+fn invalid(path: &Path, message: &str) -> ConfigError {
+    return ConfigError::InvalidInclude { path: path.to_owned(), message: message.to_string() };
+}
@@ -0,0 +1,173 @@
+/*!
+ * Expand references inside string values.
+ *
+ * Syntax             | Replaced with
+ * ${NAME}              environment variable $NAME
+ * ${mythos:DATA}       a mythos dir, see dirs::expand_mythos_shortcut(...)
+ * ${self:other.key}    the value of another key in the same config
+ * $$                   a literal '$'
+ *
+ * Any other '$' is kept as is, e.g. "$HOME" is not expanded.
+ * Off by default, see ConfigOptions::interpolate.
+ * Raw files from directory configs are never expanded, even through ${self:...}.
+ */
+use std::collections::{HashMap, HashSet};
+use toml::{Table, Value};
+use crate::dirs;
+use super::{path, ConfigError};
+
+pub(crate) fn interpolate(table: &mut Table, raw: &HashSet<String>) -> Result<(), ConfigError> {
+    //! raw holds the normalized keys of values that are kept as is.
+    let root = table.clone();
+    let mut interpolator = Interpolator { root: &root, raw, resolved: HashMap::new(), stack: Vec::new() };
+    for (key, val) in table.iter_mut() {
+        interpolator.resolve_value(&path::join(&[key]), val)?;
+    }
+    return Ok(());
+}
+
+struct Interpolator<'a> {
+    /// Unmodified config, used to look up ${self:...} references.
+    root: &'a Table,
+    raw: &'a HashSet<String>,
+    /// Normalized key path -> fully resolved value.
+    resolved: HashMap<String, String>,
+    /// Keys currently being resolved, used to detect cycles.
+    stack: Vec<String>,
+}
+
+impl Interpolator<'_> {
+    fn resolve_value(&mut self, key: &str, val: &mut Value) -> Result<(), ConfigError> {
+        match val {
+            Value::String(raw) => *raw = self.resolve_key(key, raw)?,
+            Value::Array(vals) => {
+                for (i, val) in vals.iter_mut().enumerate() {
+                    self.resolve_value(&format!("{key}[{i}]"), val)?;
+                }
+            },
+            Value::Table(table) => {
+                for (sub_key, val) in table.iter_mut() {
+                    self.resolve_value(&format!("{key}.{}", path::join(&[sub_key])), val)?;
+                }
+            },
+            _ => ()
+        }
+        return Ok(());
+    }
+    fn resolve_key(&mut self, key: &str, raw: &str) -> Result<String, ConfigError> {
+        if self.raw.contains(key) {
+            return Ok(raw.to_owned());
+        }
+        if let Some(resolved) = self.resolved.get(key) {
+            return Ok(resolved.to_owned());
+        }
+        if self.stack.iter().any(|x| x == key) {
+            let mut cycle = self.stack.clone();
+            cycle.push(key.to_string());
+            return Err(error(&self.stack[0], &format!("references form a cycle: {}", cycle.join(" -> "))));
+        }
+
+        self.stack.push(key.to_string());
+        let resolved = self.resolve_str(key, raw);
+        self.stack.pop();
+
+        let resolved = resolved?;
+        self.resolved.insert(key.to_string(), resolved.to_owned());
+        return Ok(resolved);
+    }
+    fn resolve_str(&mut self, key: &str, raw: &str) -> Result<String, ConfigError> {
+        let mut output = String::new();
+        let mut chars = raw.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('$', Some('$')) => {
+                    chars.next();
+                    output.push('$');
+                },
+                ('$', Some('{')) => {
+                    chars.next();
+                    let mut expr = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => expr.push(c),
+                            None => return Err(error(key, &format!("'${{{expr}' is missing a closing '}}'")))
+                        }
+                    }
+                    output.push_str(&self.resolve_expr(key, &expr)?);
+                },
+                _ => output.push(c)
+            }
+        }
+        return Ok(output);
+    }
+    fn resolve_expr(&mut self, key: &str, expr: &str) -> Result<String, ConfigError> {
+        if let Some(shortcut) = expr.strip_prefix("mythos:") {
+            return match dirs::expand_mythos_shortcut(shortcut, "") {
+                Some(path) => Ok(path.to_string_lossy().to_string()),
+                None => Err(error(key, &format!("'{shortcut}' is not a mythos dir")))
+            };
+        }
+        if let Some(other) = expr.strip_prefix("self:") {
+            let other_key = path::normalize(other);
+            return match path::lookup(self.root, other) {
+                Some(Value::String(raw)) => self.resolve_key(&other_key, raw),
+                Some(Value::Table(_)) | Some(Value::Array(_)) => {
+                    Err(error(key, &format!("'{other}' is a table or array, not a single value")))
+                },
+                Some(val) => Ok(val.to_string()),
+                None => Err(error(key, &format!("'{other}' does not exist")))
+            };
+        }
+        return std::env::var(expr).map_err(|_| error(key, &format!("${expr} is not set")));
+    }
+}
+
+fn error(key: &str, message: &str) -> ConfigError {
+    return ConfigError::Interpolation { key: key.to_string(), message: message.to_string() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interpolated(contents: &str) -> Result<Table, ConfigError> {
+        let mut table: Table = toml::from_str(contents).unwrap();
+        interpolate(&mut table, &HashSet::new())?;
+        return Ok(table);
+    }
+    #[test]
+    fn interpolate_values() {
+        unsafe { std::env::set_var("MYTHOS_INTERPOLATE_TEST", "env"); }
+        let table = interpolated(r#"
+            env = "${MYTHOS_INTERPOLATE_TEST}/x"
+            escaped = "$${MYTHOS_INTERPOLATE_TEST} costs $5"
+            reference = "${self:nested.list[0]}-${self:nested.int}"
+            [nested]
+            list = ["${self:env}"]
+            int = 1
+        "#).unwrap();
+        assert_eq!(table["env"], Value::String("env/x".into()));
+        assert_eq!(table["escaped"], Value::String("${MYTHOS_INTERPOLATE_TEST} costs $5".into()));
+        assert_eq!(table["reference"], Value::String("env/x-1".into()));
+        assert_eq!(table["nested"]["list"][0], Value::String("env/x".into()));
+    }
+    #[test]
+    fn interpolation_errors() {
+        let err = interpolated("a = \"${self:b}\"\nb = \"${self:a}\"").unwrap_err();
+        assert_eq!(err.to_string(), "Could not expand 'a': references form a cycle: a -> b -> a");
+
+        assert!(interpolated("a = \"${MYTHOS_NOT_SET_VAR}\"").is_err());
+        assert!(interpolated("a = \"${mythos:NOT_A_DIR}\"").is_err());
+        assert!(interpolated("a = \"${unclosed\"").is_err());
+    }
+    #[test]
+    fn skip_raw_values() {
+        let mut table: Table = toml::from_str("raw = \"${MYTHOS_NOT_SET_VAR} $$\"\nlist = [\"$$\", \"$$\"]\nref = \"${self:raw}\"").unwrap();
+        let raw = HashSet::from(["raw".to_string(), "list[1]".to_string()]);
+        interpolate(&mut table, &raw).unwrap();
+        assert_eq!(table["raw"], Value::String("${MYTHOS_NOT_SET_VAR} $$".into()));
+        assert_eq!(table["ref"], Value::String("${MYTHOS_NOT_SET_VAR} $$".into()));
+        assert_eq!(table["list"], Value::Array(vec![Value::String("$".into()), Value::String("$$".into())]));
+    }
+}
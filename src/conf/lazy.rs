@@ -0,0 +1,135 @@
+/*!
+ * Files of directory configs that are read the first time they are accessed, see ConfigOptions::lazy_dirs.
+ * Lazy files are keyed by their normalized path in the config, e.g. "servers.web" for servers/web.toml.
+ *
+ * Reading only requires &self, so a lazy file can be read while the config is borrowed.
+ * Anything that modifies the config reads every lazy file first, see MythosConfig::load_lazy(...).
+ */
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml::{Table, Value};
+use crate::{self as mythos_core, printwarn};
+use super::{interpolate, origin, path, MythosConfig, Origin};
+
+pub(crate) type LazyFiles = BTreeMap<String, LazyFile>;
+
+#[derive(Debug, Clone)]
+pub(crate) struct LazyFile {
+    path: PathBuf,
+    /// Expand ${...} references once the file is read. ${self:...} can only reference keys in the same file.
+    interpolate: bool,
+    /// None if the file could not be read.
+    loaded: OnceLock<Option<Loaded>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Loaded {
+    /// Always a table.
+    pub value: Value,
+    /// value before references were expanded, which is what save() writes.
+    pub raw: Value,
+    pub origins: origin::Origins,
+}
+
+impl LazyFile {
+    pub fn new(path: &Path, interpolate: bool) -> LazyFile {
+        return LazyFile { path: path.to_owned(), interpolate, loaded: OnceLock::new() };
+    }
+    pub fn load(&self) -> Option<&Loaded> {
+        return self.loaded.get_or_init(|| {
+            let mut file = match MythosConfig::read_file(&self.path) {
+                Ok(file) => file,
+                Err(err) => {
+                    printwarn!("{err}\nTried to read {:?} as config file. File was skipped.", self.path);
+                    return None;
+                }
+            };
+            let raw = Value::Table(file.table.clone());
+            if self.interpolate {
+                if let Err(err) = interpolate::interpolate(&mut file.table, &origin::raw_keys(&file.origins)) {
+                    printwarn!("{err}\nIn {:?}. File was skipped.", self.path);
+                    return None;
+                }
+            }
+            Some(Loaded { value: Value::Table(file.table), raw, origins: file.origins })
+        }).as_ref();
+    }
+    pub fn into_loaded(self) -> Option<Loaded> {
+        self.load();
+        return self.loaded.into_inner().flatten();
+    }
+}
+
+pub(crate) fn get<'a>(lazy: &'a LazyFiles, key: &str) -> Option<&'a Value> {
+    //! Read the file that contains key, then find key in it.
+    let (loaded, rest) = find(lazy, key)?;
+    if rest.is_empty() {
+        return Some(&loaded.value);
+    }
+    return path::lookup(loaded.value.as_table()?, &path::to_path(&rest));
+}
+
+pub(crate) fn origin<'a>(lazy: &'a LazyFiles, key: &str) -> Option<&'a Origin> {
+    let (loaded, rest) = find(lazy, key)?;
+    return origin::find(&loaded.origins, &path::to_path(&rest));
+}
+
+pub(crate) fn keys_under(lazy: &LazyFiles, prefix: &str) -> Vec<String> {
+    //! Keys of the lazy files directly inside the table at prefix. prefix is empty for the root.
+    let parent = path::parse(prefix).unwrap_or_default();
+    return lazy.keys()
+        .filter_map(|key| {
+            let segments = path::parse(key)?;
+            if segments.len() != parent.len() + 1 || segments[..parent.len()] != parent[..] {
+                return None;
+            }
+            match segments.last() {
+                Some(path::Segment::Key(key)) => Some(key.to_owned()),
+                _ => None
+            }
+        })
+        .collect();
+}
+
+pub(crate) fn fill(table: &mut Table, lazy: &LazyFiles, prefix: &str) -> bool {
+    //! Read the lazy files inside the table at prefix into table, a copy of it. prefix is empty for the root.
+    //! Returns false if there were none, i.e. table is unchanged.
+    let mut filled = false;
+    for (key, file) in lazy {
+        let key = match prefix.is_empty() {
+            true => Some(key.as_str()),
+            false => key.strip_prefix(prefix).and_then(|x| x.strip_prefix('.')),
+        };
+        if let (Some(segments), Some(loaded)) = (key.and_then(path::parse), key.and_then(|_| file.load())) {
+            path::insert(table, &segments, loaded.value.clone());
+            filled = true;
+        }
+    }
+    return filled;
+}
+
+pub(crate) fn rebase(lazy: &LazyFiles, prefix: &str) -> LazyFiles {
+    //! Lazy files inside the table at prefix, keyed relative to it.
+    if prefix.is_empty() {
+        return lazy.clone();
+    }
+    let prefix = format!("{prefix}.");
+    return lazy.iter()
+        .filter_map(|(key, file)| Some((key.strip_prefix(&prefix)?.to_string(), file.to_owned())))
+        .collect();
+}
+
+fn find<'a>(lazy: &'a LazyFiles, key: &str) -> Option<(&'a Loaded, Vec<path::Segment>)> {
+    //! The lazy file that contains key, and the rest of key inside that file.
+    if lazy.is_empty() {
+        return None;
+    }
+    let segments = path::parse(key)?;
+    for i in 1..=segments.len() {
+        if let Some(file) = lazy.get(&path::to_path(&segments[..i])) {
+            return Some((file.load()?, segments[i..].to_vec()));
+        }
+    }
+    return None;
+}
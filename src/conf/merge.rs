@@ -0,0 +1,70 @@
+use toml::{Table, Value};
+
+/// How arrays are combined when two layers set the same key.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The later layer's array replaces the earlier one.
+    #[default]
+    Replace,
+    /// The later layer's elements are appended to the earlier one.
+    Append,
+    /// Like Append, but elements already present are skipped.
+    UniqueAppend,
+}
+
+pub(crate) fn merge_tables(base: &mut Table, overlay: Table, arrays: ArrayMerge) {
+    //! Deep-merge overlay into base.
+    //! Tables are merged key by key, arrays according to {arrays}, everything else is replaced.
+    for (key, new_val) in overlay {
+        match (base.get_mut(&key), new_val) {
+            (Some(Value::Table(old)), Value::Table(new)) => merge_tables(old, new, arrays),
+            (Some(Value::Array(old)), Value::Array(new)) => merge_arrays(old, new, arrays),
+            (_, new_val) => {
+                base.insert(key, new_val);
+            }
+        }
+    }
+}
+
+fn merge_arrays(base: &mut Vec<Value>, overlay: Vec<Value>, arrays: ArrayMerge) {
+    match arrays {
+        ArrayMerge::Replace => *base = overlay,
+        ArrayMerge::Append => base.extend(overlay),
+        ArrayMerge::UniqueAppend => {
+            for val in overlay {
+                if !base.contains(&val) {
+                    base.push(val);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables() -> (Table, Table) {
+        let base = toml::from_str("list = [1, 2]\n[dict]\na = 1\nb = 2").unwrap();
+        let overlay = toml::from_str("list = [2, 3]\n[dict]\nb = 3").unwrap();
+        return (base, overlay);
+    }
+    #[test]
+    fn merge_replace() {
+        let (mut base, overlay) = tables();
+        merge_tables(&mut base, overlay, ArrayMerge::Replace);
+        assert_eq!(base["list"], Value::Array(vec![2.into(), 3.into()]));
+        assert_eq!(base["dict"]["a"], Value::Integer(1));
+        assert_eq!(base["dict"]["b"], Value::Integer(3));
+    }
+    #[test]
+    fn merge_append() {
+        let (mut base, overlay) = tables();
+        merge_tables(&mut base, overlay, ArrayMerge::Append);
+        assert_eq!(base["list"], Value::Array(vec![1.into(), 2.into(), 2.into(), 3.into()]));
+
+        let (mut base, overlay) = tables();
+        merge_tables(&mut base, overlay, ArrayMerge::UniqueAppend);
+        assert_eq!(base["list"], Value::Array(vec![1.into(), 2.into(), 3.into()]));
+    }
+}
@@ -0,0 +1,96 @@
+/*!
+ * Versioned migrations, for utils that rename or restructure their keys.
+ * e.g.
+ * let migrations = Migrations::new()
+ *     .migration(Migration::new(2).rename("colour", "color").move_into("width", "display"))
+ *     .migration(Migration::new(3).transform("timeout", |x| Some(ConfigValue::Integer(x.as_integer()? * 1000))));
+ * config.migrate(&migrations)?;
+ *
+ * Step                     | Effect
+ * rename("a.b", "c")         a.b is moved to c
+ * move_into("a.b", "d")      a.b is moved to d.b
+ * transform("a", f)          a is replaced with f(a), or removed if f returns None
+ *
+ * A config's version is its config_version key, or 0 if it has none.
+ * Each migration upgrades a config to its version, so it only runs on configs with an older one.
+ * Steps on keys that do not exist are skipped.
+ */
+use super::ConfigValue;
+
+pub const VERSION_KEY: &str = "config_version";
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    Rename { from: String, to: String },
+    MoveInto { key: String, section: String },
+    /// Returning None removes key.
+    Transform { key: String, transform: fn(&ConfigValue) -> Option<ConfigValue> },
+}
+
+/// Every step needed to upgrade a config to version.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub version: i64,
+    pub steps: Vec<Step>,
+}
+
+impl Migration {
+    pub fn new(version: i64) -> Migration {
+        return Migration { version, steps: Vec::new() };
+    }
+    pub fn rename(mut self, from: &str, to: &str) -> Migration {
+        self.steps.push(Step::Rename { from: from.to_string(), to: to.to_string() });
+        return self;
+    }
+    pub fn move_into(mut self, key: &str, section: &str) -> Migration {
+        self.steps.push(Step::MoveInto { key: key.to_string(), section: section.to_string() });
+        return self;
+    }
+    pub fn transform(mut self, key: &str, transform: fn(&ConfigValue) -> Option<ConfigValue>) -> Migration {
+        self.steps.push(Step::Transform { key: key.to_string(), transform });
+        return self;
+    }
+}
+
+/// Every migration a util has registered. The order they are added in does not matter.
+#[derive(Debug, Clone, Default)]
+pub struct Migrations {
+    pub migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    pub fn new() -> Migrations {
+        return Migrations::default();
+    }
+    pub fn migration(mut self, migration: Migration) -> Migrations {
+        self.migrations.push(migration);
+        return self;
+    }
+    pub fn latest(&self) -> i64 {
+        //! The version configs are upgraded to.
+        return self.migrations.iter().map(|x| x.version).max().unwrap_or(0);
+    }
+    pub(crate) fn pending(&self, version: i64) -> Vec<&Migration> {
+        //! Migrations newer than version, oldest first.
+        let mut pending: Vec<&Migration> = self.migrations.iter().filter(|x| x.version > version).collect();
+        pending.sort_by_key(|x| x.version);
+        return pending;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_migrations() {
+        let migrations = Migrations::new()
+            .migration(Migration::new(3))
+            .migration(Migration::new(1))
+            .migration(Migration::new(2));
+        assert_eq!(migrations.latest(), 3);
+        let versions: Vec<i64> = migrations.pending(1).iter().map(|x| x.version).collect();
+        assert_eq!(versions, vec![2, 3]);
+        assert!(migrations.pending(3).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+/*!
+ * Where each value in a config came from.
+ * Origins are tracked per leaf, i.e. every value that is not a table.
+ * Arrays are leaves, so their elements share the array's origin,
+ * except for raw files in directory arrays, which are tracked per element, e.g. "templates[1]".
+ */
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use super::path;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Origin {
+    /// Read from a file. line is 1-indexed, or None if the format does not track lines (JSON, YAML).
+    File { path: PathBuf, line: Option<usize> },
+    /// A file in a directory config that was read as is, see ConfigOptions::raw_extensions. Never interpolated.
+    Raw(PathBuf),
+    /// Parsed by MythosConfig::from_str(...), e.g. a default config embedded in the util. line is 1-indexed.
+    Embedded { line: Option<usize> },
+    /// Set by an environment variable, see ConfigOptions::env_overrides.
+    Env(String),
+    /// Filled in by apply_defaults(...).
+    Default,
+    /// Set by set_*(...), and not saved yet.
+    Set,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Origin::File { path, line: Some(line) } => write!(f, "{}:{line}", path.display()),
+            Origin::File { path, line: None } => write!(f, "{}", path.display()),
+            Origin::Raw(path) => write!(f, "{}", path.display()),
+            Origin::Embedded { line: Some(line) } => write!(f, "embedded config:{line}"),
+            Origin::Embedded { line: None } => write!(f, "embedded config"),
+            Origin::Env(var) => write!(f, "environment variable ${var}"),
+            Origin::Default => write!(f, "schema default"),
+            Origin::Set => write!(f, "set at runtime"),
+        };
+    }
+}
+
+pub(crate) type Origins = BTreeMap<String, Origin>;
+
+pub(crate) fn raw_keys(origins: &Origins) -> HashSet<String> {
+    //! Keys whose values were read from raw files.
+    return origins.iter().filter(|(_, origin)| matches!(origin, Origin::Raw(_))).map(|(key, _)| key.to_owned()).collect();
+}
+
+pub(crate) fn from_file(path: &Path, table: &Table, lines: &BTreeMap<String, usize>) -> Origins {
+    //! Every leaf in table was read from path. lines maps keys to the line they were set on.
+    return leaves(table).into_iter()
+        .map(|key| {
+            let line = lines.get(&key).copied();
+            (key, Origin::File { path: path.to_owned(), line })
+        })
+        .collect();
+}
+
+pub(crate) fn leaves(table: &Table) -> Vec<String> {
+    //! Normalized path of every leaf in table.
+    let mut keys = Vec::new();
+    for (key, val) in table {
+        keys.extend(leaves_of(&path::join(&[key]), val));
+    }
+    return keys;
+}
+
+pub(crate) fn leaves_of(key: &str, val: &Value) -> Vec<String> {
+    //! Normalized path of every leaf in val, which is found at key.
+    return match val {
+        Value::Table(table) => leaves(table).into_iter().map(|x| format!("{key}.{x}")).collect(),
+        _ => vec![path::normalize(key)],
+    };
+}
+
+pub(crate) fn find<'a>(origins: &'a Origins, key: &str) -> Option<&'a Origin> {
+    //! Origin of key, or of the array key is in.
+    let segments = path::parse(key)?;
+    return (1..=segments.len()).rev().find_map(|i| origins.get(&path::to_path(&segments[..i])));
+}
+
+pub(crate) fn prune(origins: &mut Origins, table: &Table) {
+    //! Forget keys that were replaced or removed, e.g. a.b after a table a is replaced with a string.
+    origins.retain(|key, _| path::lookup(table, key).is_some_and(|x| !x.is_table()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_leaves() {
+        let mut table: Table = toml::from_str("a = 1\n[b]\n\"c.d\" = [1, 2]\n[b.e]\nf = true").unwrap();
+        assert_eq!(leaves(&table), vec!["a", "b.\"c.d\"", "b.e.f"]);
+
+        let mut origins = from_file(Path::new("x.toml"), &table, &BTreeMap::from([("a".to_string(), 1)]));
+        assert_eq!(find(&origins, "a").unwrap().to_string(), "x.toml:1");
+        assert_eq!(find(&origins, "b.'c.d'[1]").unwrap().to_string(), "x.toml");
+        assert_eq!(find(&origins, "b"), None);
+
+        table.insert("b".into(), Value::Integer(1));
+        prune(&mut origins, &table);
+        assert_eq!(origins.keys().collect::<Vec<&String>>(), vec!["a"]);
+    }
+}
@@ -0,0 +1,205 @@
+/*!
+ * Key paths used to reach nested values.
+ *
+ * Syntax         | Meaning
+ * a.b.c            key c in table b in table a
+ * a.b[1]           second element of array b in table a
+ * a."b.c"          key "b.c" in table a, quotes may be double or single
+ */
+use toml::{Table, Value};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+pub(crate) fn parse(path: &str) -> Option<Vec<Segment>> {
+    //! Split path into segments. Returns None if path is malformed.
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    // Whether the next char must start a key, e.g. after '.' or at the start of the path.
+    let mut expect_key = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' if !expect_key => expect_key = true,
+            '.' => return None,
+            '[' if !expect_key => {
+                let mut index = String::new();
+                loop {
+                    match chars.next()? {
+                        ']' => break,
+                        c => index.push(c),
+                    }
+                }
+                segments.push(Segment::Index(index.trim().parse().ok()?));
+                expect_key = false;
+            },
+            '"' | '\'' if expect_key => {
+                let mut key = String::new();
+                loop {
+                    match chars.next()? {
+                        '\\' if c == '"' => key.push(chars.next()?),
+                        x if x == c => break,
+                        x => key.push(x),
+                    }
+                }
+                segments.push(Segment::Key(key));
+                expect_key = false;
+            },
+            _ if expect_key => {
+                let mut key = String::from(c);
+                while let Some(c) = chars.peek() {
+                    if ['.', '['].contains(c) {
+                        break;
+                    }
+                    key.push(chars.next()?);
+                }
+                segments.push(Segment::Key(key.trim().to_string()));
+                expect_key = false;
+            },
+            _ => return None,
+        }
+    }
+    if expect_key {
+        return None;
+    }
+    return Some(segments);
+}
+
+pub fn join<S>(keys: &[S]) -> String where S: AsRef<str> {
+    //! Inverse of parse(...) for paths without indexes. Keys are quoted when needed.
+    return keys.iter().map(|key| {
+        let key = key.as_ref();
+        if key.is_empty() || key.contains(['.', '[', ']', '"', '\'', '\\']) || key.trim() != key {
+            format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+        } else {
+            key.to_string()
+        }
+    }).collect::<Vec<String>>().join(".");
+}
+
+pub(crate) fn normalize(path: &str) -> String {
+    //! Convert path into the form produced by join(...), so equivalent paths compare equal.
+    //! Indexes are written as [i]. Malformed paths are returned unchanged.
+    return match parse(path) {
+        Some(segments) => to_path(&segments),
+        None => path.to_string()
+    };
+}
+
+pub(crate) fn to_path(segments: &[Segment]) -> String {
+    //! Inverse of parse(...).
+    let mut path = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Key(key) if path.is_empty() => path.push_str(&join(&[key])),
+            Segment::Key(key) => path.push_str(&format!(".{}", join(&[key]))),
+            Segment::Index(i) => path.push_str(&format!("[{i}]")),
+        }
+    }
+    return path;
+}
+
+pub(crate) fn lookup<'a>(table: &'a Table, path: &str) -> Option<&'a Value> {
+    //! Find the value at path.
+    //! Keys that exist verbatim (e.g. "a.b" when the table contains a key named "a.b")
+    //! are preferred over path parsing, so older configs keep working.
+    if let Some(val) = table.get(path) {
+        return Some(val);
+    }
+    let segments = parse(path)?;
+    let (first, rest) = segments.split_first()?;
+    let mut val = match first {
+        Segment::Key(key) => table.get(key)?,
+        Segment::Index(_) => return None,
+    };
+    for segment in rest {
+        val = match (segment, val) {
+            (Segment::Key(key), Value::Table(table)) => table.get(key)?,
+            (Segment::Index(i), Value::Array(array)) => array.get(*i)?,
+            _ => return None,
+        };
+    }
+    return Some(val);
+}
+
+pub(crate) fn insert(table: &mut Table, segments: &[Segment], value: Value) -> bool {
+    //! Set the value at segments, creating tables as needed.
+    //! Returns false if segments contains an index or a parent is not a table.
+    let (last, parents) = match segments.split_last() {
+        Some((Segment::Key(last), parents)) => (last, parents),
+        _ => return false
+    };
+    let mut table = table;
+    for segment in parents {
+        let key = match segment {
+            Segment::Key(key) => key,
+            Segment::Index(_) => return false
+        };
+        table = match table.entry(key).or_insert(Value::Table(Table::new())) {
+            Value::Table(table) => table,
+            _ => return false
+        };
+    }
+    table.insert(last.to_owned(), value);
+    return true;
+}
+
+pub(crate) fn remove(table: &mut Table, segments: &[Segment]) -> Option<Value> {
+    let (last, parents) = match segments.split_last() {
+        Some((Segment::Key(last), parents)) => (last, parents),
+        _ => return None
+    };
+    let mut table = table;
+    for segment in parents {
+        let key = match segment {
+            Segment::Key(key) => key,
+            Segment::Index(_) => return None
+        };
+        table = match table.get_mut(key) {
+            Some(Value::Table(table)) => table,
+            _ => return None
+        };
+    }
+    return table.remove(last);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Segment::*;
+
+    #[test]
+    fn parse_paths() {
+        assert_eq!(parse("a"), Some(vec![Key("a".into())]));
+        assert_eq!(parse("a.b[1]"), Some(vec![Key("a".into()), Key("b".into()), Index(1)]));
+        assert_eq!(parse("a.\"b.c\".d"), Some(vec![Key("a".into()), Key("b.c".into()), Key("d".into())]));
+        assert_eq!(parse("'a\\b'[0][2]"), Some(vec![Key("a\\b".into()), Index(0), Index(2)]));
+        assert_eq!(parse("a."), None);
+        assert_eq!(parse("a..b"), None);
+        assert_eq!(parse("a[x]"), None);
+    }
+    #[test]
+    fn join_paths() {
+        assert_eq!(join(&["a", "b.c", "d"]), "a.\"b.c\".d");
+        assert_eq!(normalize("a.'b.c'[1]"), "a.\"b.c\"[1]");
+        assert_eq!(parse(&join(&["a\"b", "c\\"])), Some(vec![Key("a\"b".into()), Key("c\\".into())]));
+    }
+    #[test]
+    fn lookup_paths() {
+        let table: Table = toml::from_str(r#"
+            "a.b" = 1
+            [server]
+            ports = [80, 443]
+            [server."host.name"]
+            value = "x"
+        "#).unwrap();
+        assert_eq!(lookup(&table, "a.b"), Some(&Value::Integer(1)));
+        assert_eq!(lookup(&table, "server.ports[1]"), Some(&Value::Integer(443)));
+        assert_eq!(lookup(&table, "server.\"host.name\".value"), Some(&Value::String("x".into())));
+        assert_eq!(lookup(&table, "server.ports[2]"), None);
+        assert_eq!(lookup(&table, "server.ports.x"), None);
+    }
+}
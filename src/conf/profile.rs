@@ -0,0 +1,107 @@
+/*!
+ * Named profiles, overlays that live in the config's [profile.NAME] tables.
+ * e.g.
+ * editor = "vim"
+ * [profile.base]
+ * theme = "dark"
+ * [profile.work]
+ * inherits = "base"
+ * editor = "code"
+ *
+ * Source                       | Precedence
+ * ConfigOptions::profile         highest
+ * $MYTHOS_<UTIL>_PROFILE
+ * $MYTHOS_PROFILE                lowest, ignored by utils that don't define the profile
+ *
+ * The active profile is deep-merged over the base table, after the profiles it inherits from.
+ */
+use toml::{Table, Value};
+use super::{env, ConfigError};
+
+pub(crate) const SECTION: &str = "profile";
+const INHERITS: &str = "inherits";
+
+pub(crate) fn var(util_name: &str) -> String {
+    return format!("{}_PROFILE", env::util_var(util_name));
+}
+
+pub(crate) fn select(defined: &[String], util_name: &str, requested: Option<&str>) -> Option<String> {
+    //! Name of the active profile, if any. defined lists the config's profiles.
+    if let Some(name) = requested {
+        return Some(name.to_string());
+    }
+    if let Some(name) = std::env::var(var(util_name)).ok().filter(|x| !x.is_empty()) {
+        return Some(name);
+    }
+    // $MYTHOS_PROFILE is shared by every util, most of which won't define it.
+    let name = std::env::var("MYTHOS_PROFILE").ok().filter(|x| !x.is_empty())?;
+    return defined.contains(&name).then_some(name);
+}
+
+pub(crate) fn resolve(table: &Table, name: &str) -> Result<Vec<(String, Table)>, ConfigError> {
+    //! name and every profile it inherits from, starting with the furthest ancestor.
+    //! inherits is removed from the returned tables.
+    let mut chain: Vec<(String, Table)> = Vec::new();
+    let mut next = Some(name.to_string());
+    while let Some(name) = next {
+        if chain.iter().any(|(x, _)| *x == name) {
+            let mut cycle: Vec<String> = chain.into_iter().map(|(x, _)| x).collect();
+            cycle.push(name);
+            return Err(ConfigError::ProfileCycle(cycle));
+        }
+        let mut profile = match profiles(table).and_then(|x| x.get(&name)) {
+            Some(Value::Table(profile)) => profile.to_owned(),
+            Some(_) => return Err(ConfigError::InvalidProfile { name, message: "profile is not a table".into() }),
+            None => return Err(ConfigError::InvalidProfile { name, message: "profile is not defined".into() }),
+        };
+        next = match profile.remove(INHERITS) {
+            Some(Value::String(parent)) => Some(parent),
+            Some(_) => return Err(ConfigError::InvalidProfile { name, message: format!("'{INHERITS}' is not a string") }),
+            None => None,
+        };
+        chain.push((name, profile));
+    }
+    chain.reverse();
+    return Ok(chain);
+}
+
+fn profiles(table: &Table) -> Option<&Table> {
+    return table.get(SECTION)?.as_table();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles() -> Table {
+        return toml::from_str(r#"
+            [profile.a]
+            val = 1
+            [profile.b]
+            inherits = "a"
+            [profile.c]
+            inherits = "b"
+            [profile.loop1]
+            inherits = "loop2"
+            [profile.loop2]
+            inherits = "loop1"
+        "#).unwrap();
+    }
+
+    #[test]
+    fn resolve_inherits() {
+        let table = profiles();
+        let names: Vec<String> = resolve(&table, "c").unwrap().into_iter().map(|x| x.0).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert!(resolve(&table, "c").unwrap().iter().all(|x| !x.1.contains_key(INHERITS)));
+        assert!(matches!(resolve(&table, "missing"), Err(ConfigError::InvalidProfile { .. })));
+        match resolve(&table, "loop1") {
+            Err(ConfigError::ProfileCycle(cycle)) => assert_eq!(cycle, vec!["loop1", "loop2", "loop1"]),
+            _ => panic!("expected a cycle")
+        }
+    }
+    #[test]
+    fn profile_var() {
+        assert_eq!(var("mythos-conf"), "MYTHOS_MYTHOS_CONF_PROFILE");
+    }
+}
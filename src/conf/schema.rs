@@ -0,0 +1,174 @@
+/*!
+ * Declare the keys a util expects, then check a config against them.
+ * e.g.
+ * let schema = ConfigSchema::new()
+ *     .key(KeySpec::new("width", ValueKind::Integer).default(80).range(1.0, 500.0))
+ *     .key(KeySpec::new("theme.name", ValueKind::String).required().allowed(["dark", "light"]));
+ * config.apply_defaults(&schema);
+ * if let Err(violations) = config.validate(&schema) { ... }
+ */
+use std::fmt;
+use std::path::PathBuf;
+use toml::Value;
+use super::value::{self, ConfigValue};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ValueKind { String, Integer, Float, Boolean, Datetime, Array, Table }
+
+impl ValueKind {
+    pub fn of(val: &ConfigValue) -> ValueKind {
+        return val.kind();
+    }
+    pub(crate) fn of_toml(val: &Value) -> ValueKind {
+        return match val {
+            Value::String(_) => ValueKind::String,
+            Value::Integer(_) => ValueKind::Integer,
+            Value::Float(_) => ValueKind::Float,
+            Value::Boolean(_) => ValueKind::Boolean,
+            Value::Datetime(_) => ValueKind::Datetime,
+            Value::Array(_) => ValueKind::Array,
+            Value::Table(_) => ValueKind::Table,
+        };
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", format!("{self:?}").to_lowercase());
+    }
+}
+
+/// Describes a single key. key can be a path, e.g. "theme.name".
+#[derive(Debug, Clone)]
+pub struct KeySpec {
+    pub key: String,
+    pub kind: ValueKind,
+    pub required: bool,
+    /// Used by apply_defaults(...) when key is missing. A key with a default is never reported as missing.
+    pub default: Option<ConfigValue>,
+    /// Inclusive bounds for Integer and Float keys.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// If not empty, the value must be one of these.
+    pub allowed: Vec<ConfigValue>,
+    pub description: String,
+}
+
+impl KeySpec {
+    pub fn new(key: &str, kind: ValueKind) -> KeySpec {
+        return KeySpec {
+            key: key.to_string(),
+            kind,
+            required: false,
+            default: None,
+            min: None,
+            max: None,
+            allowed: Vec::new(),
+            description: String::new(),
+        };
+    }
+    pub fn required(mut self) -> KeySpec {
+        self.required = true;
+        return self;
+    }
+    pub fn default<V>(mut self, val: V) -> KeySpec where V: Into<ConfigValue> {
+        self.default = Some(val.into());
+        return self;
+    }
+    pub fn range(mut self, min: f64, max: f64) -> KeySpec {
+        self.min = Some(min);
+        self.max = Some(max);
+        return self;
+    }
+    pub fn allowed<I, V>(mut self, vals: I) -> KeySpec where I: IntoIterator<Item = V>, V: Into<ConfigValue> {
+        self.allowed = vals.into_iter().map(|x| x.into()).collect();
+        return self;
+    }
+    pub fn description(mut self, description: &str) -> KeySpec {
+        self.description = description.to_string();
+        return self;
+    }
+    pub(crate) fn check(&self, val: Option<&Value>) -> Option<Violation> {
+        //! Returns the first rule that val breaks.
+        let val = match val {
+            Some(val) => val,
+            None if self.required && self.default.is_none() => return Some(Violation::Missing),
+            None => return None
+        };
+        if ValueKind::of_toml(val) != self.kind {
+            return Some(Violation::WrongType { expected: self.kind, found: ValueKind::of_toml(val) });
+        }
+        let num = match val {
+            Value::Integer(val) => Some(*val as f64),
+            Value::Float(val) => Some(*val),
+            _ => None
+        };
+        if let Some(num) = num {
+            if self.min.is_some_and(|min| num < min) || self.max.is_some_and(|max| num > max) {
+                return Some(Violation::OutOfRange { min: self.min, max: self.max });
+            }
+        }
+        if !self.allowed.is_empty() && !self.allowed.contains(&value::from_toml(val)) {
+            return Some(Violation::NotAllowed { allowed: self.allowed.clone() });
+        }
+        return None;
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ConfigSchema {
+    pub keys: Vec<KeySpec>,
+}
+
+impl ConfigSchema {
+    pub fn new() -> ConfigSchema {
+        return ConfigSchema::default();
+    }
+    pub fn key(mut self, spec: KeySpec) -> ConfigSchema {
+        self.keys.push(spec);
+        return self;
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Violation {
+    Missing,
+    WrongType { expected: ValueKind, found: ValueKind },
+    OutOfRange { min: Option<f64>, max: Option<f64> },
+    NotAllowed { allowed: Vec<ConfigValue> },
+}
+
+/// A key that does not match its KeySpec.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SchemaViolation {
+    pub key: String,
+    /// File the key was read from, if known.
+    pub file: Option<PathBuf>,
+    /// The offending value, if the key exists.
+    pub value: Option<ConfigValue>,
+    pub violation: Violation,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        let value = self.value.as_ref().map(|x| x.to_string()).unwrap_or_default();
+        return match &self.violation {
+            Violation::Missing => write!(f, "'{}' is required, but missing", self.key),
+            Violation::WrongType { expected, found } => {
+                write!(f, "'{}' should be {expected}, found {found} {value}", self.key)
+            },
+            Violation::OutOfRange { min, max } => {
+                let min = min.map(|x| x.to_string()).unwrap_or("-inf".into());
+                let max = max.map(|x| x.to_string()).unwrap_or("inf".into());
+                write!(f, "'{}' = {value} is outside of range [{min}, {max}]", self.key)
+            },
+            Violation::NotAllowed { allowed } => {
+                let allowed: Vec<String> = allowed.iter().map(|x| x.to_string()).collect();
+                write!(f, "'{}' = {value} is not one of: {}", self.key, allowed.join(", "))
+            },
+        };
+    }
+}
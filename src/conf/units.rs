@@ -0,0 +1,215 @@
+/*!
+ * Parsers for human-friendly values, see get_duration(...), get_size(...), get_path(...) and get_color(...).
+ *
+ * Getter       | Accepts
+ * duration       "5m30s", "1.5h", "250ms", or an integer number of seconds
+ * size           "10MiB", "1.5 GB", "64k", or an integer number of bytes
+ * path           "~/notes", "$HOME/notes", "$LD/util" (a mythos dir), "$VAR/notes" (an environment variable)
+ * color          "#f80", "#ff8800" or "#ff880080"
+ *
+ * Duration units are ns, us, ms, s, m, h, d and w. Several can be combined, e.g. "1h 30m".
+ * Size units are case-insensitive. kb, mb, gb and tb are powers of 1000,
+ * while k, m, g, t, kib, mib, gib and tib are powers of 1024.
+ */
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+use toml::Value;
+use crate::dirs;
+
+/// An RGBA color. a is 255 unless the value had an alpha channel.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        return Ok(());
+    }
+}
+
+pub(crate) fn duration(val: &Value) -> Result<Duration, String> {
+    let raw = match val {
+        Value::Integer(secs) => return u64::try_from(*secs).map(Duration::from_secs).map_err(|_| "duration is negative".into()),
+        Value::String(raw) => raw,
+        _ => return Err("expected a duration, e.g. \"5m30s\"".into())
+    };
+    let mut secs = 0.0;
+    for (amount, unit) in quantities(raw)? {
+        secs += amount * match unit.as_str() {
+            "ns" => 1e-9,
+            "us" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            "w" => 604800.0,
+            "" => return Err(format!("'{raw}' is missing a unit, e.g. \"{amount}s\"")),
+            _ => return Err(format!("'{unit}' is not a unit of time"))
+        };
+    }
+    return Duration::try_from_secs_f64(secs).map_err(|err| err.to_string());
+}
+
+pub(crate) fn size(val: &Value) -> Result<u64, String> {
+    let raw = match val {
+        Value::Integer(bytes) => return u64::try_from(*bytes).map_err(|_| "size is negative".into()),
+        Value::String(raw) => raw,
+        _ => return Err("expected a size, e.g. \"10MiB\"".into())
+    };
+    let parts = quantities(raw)?;
+    let (amount, unit) = match parts.as_slice() {
+        [part] => part,
+        _ => return Err(format!("'{raw}' is not a single size"))
+    };
+    let factor: u64 = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000_u64.pow(2),
+        "gb" => 1000_u64.pow(3),
+        "tb" => 1000_u64.pow(4),
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(format!("'{unit}' is not a unit of size"))
+    };
+    let bytes = amount * factor as f64;
+    if bytes > u64::MAX as f64 {
+        return Err(format!("'{raw}' is too large"));
+    }
+    return Ok(bytes.round() as u64);
+}
+
+pub(crate) fn path(val: &Value) -> Result<PathBuf, String> {
+    let raw = match val {
+        Value::String(raw) => raw,
+        _ => return Err("expected a path".into())
+    };
+    let (first, rest) = match raw.split_once('/') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (raw.as_str(), None)
+    };
+    if first != "~" && !first.starts_with('$') {
+        return Ok(PathBuf::from(raw));
+    }
+    let base = match dirs::expand_mythos_shortcut(first, "") {
+        Some(base) => base,
+        None => match std::env::var(first.trim_start_matches('$')) {
+            Ok(base) => PathBuf::from(base),
+            Err(_) => return Err(format!("'{first}' is not a mythos dir or a set environment variable"))
+        }
+    };
+    return Ok(match rest {
+        Some(rest) => base.join(rest),
+        None => base
+    });
+}
+
+pub(crate) fn color(val: &Value) -> Result<Color, String> {
+    let raw = match val {
+        Value::String(raw) => raw,
+        _ => return Err("expected a color, e.g. \"#ff8800\"".into())
+    };
+    let hex = raw.strip_prefix('#').ok_or(format!("'{raw}' is not a hex color, e.g. \"#ff8800\""))?;
+    if !hex.chars().all(|x| x.is_ascii_hexdigit()) {
+        return Err(format!("'{raw}' is not a hex color"));
+    }
+    // #rgb is short for #rrggbb.
+    let hex = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|x| [x, x]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return Err(format!("'{raw}' must have 3, 4, 6 or 8 hex digits"))
+    };
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(255);
+    return Ok(Color {
+        r: channel(0),
+        g: channel(2),
+        b: channel(4),
+        a: if hex.len() == 8 { channel(6) } else { 255 },
+    });
+}
+
+fn quantities(raw: &str) -> Result<Vec<(f64, String)>, String> {
+    //! Split e.g. "1h 30.5m" into [(1.0, "h"), (30.5, "m")].
+    let mut parts = Vec::new();
+    let mut chars = raw.trim().chars().peekable();
+    while chars.peek().is_some() {
+        let amount: String = std::iter::from_fn(|| chars.next_if(|x| x.is_ascii_digit() || *x == '.')).collect();
+        while chars.next_if(|x| x.is_whitespace()).is_some() {}
+        let unit: String = std::iter::from_fn(|| chars.next_if(|x| x.is_alphabetic())).collect();
+        while chars.next_if(|x| x.is_whitespace()).is_some() {}
+        let amount: f64 = match amount.parse() {
+            Ok(amount) => amount,
+            Err(_) => return Err(format!("'{raw}' is not a number followed by a unit"))
+        };
+        parts.push((amount, unit));
+    }
+    if parts.is_empty() {
+        return Err("value is empty".into());
+    }
+    return Ok(parts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(raw: &str) -> Value {
+        return Value::String(raw.to_string());
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(duration(&string("5m30s")), Ok(Duration::from_secs(330)));
+        assert_eq!(duration(&string("1h 30m")), Ok(Duration::from_secs(5400)));
+        assert_eq!(duration(&string("1.5s")), Ok(Duration::from_millis(1500)));
+        assert_eq!(duration(&string("250ms")), Ok(Duration::from_millis(250)));
+        assert_eq!(duration(&Value::Integer(10)), Ok(Duration::from_secs(10)));
+        assert!(duration(&string("5")).is_err());
+        assert!(duration(&string("5 parsecs")).is_err());
+        assert!(duration(&string("m")).is_err());
+        assert!(duration(&Value::Integer(-1)).is_err());
+    }
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(size(&string("10MiB")), Ok(10 * 1024 * 1024));
+        assert_eq!(size(&string("10 MB")), Ok(10_000_000));
+        assert_eq!(size(&string("1.5k")), Ok(1536));
+        assert_eq!(size(&string("512")), Ok(512));
+        assert_eq!(size(&Value::Integer(64)), Ok(64));
+        assert!(size(&string("10 MiB 5 KiB")).is_err());
+        assert!(size(&string("10 XB")).is_err());
+    }
+    #[test]
+    fn parse_paths() {
+        unsafe {
+            std::env::set_var("MYTHOS_UNITS_TESTER", "/tmp/units");
+        }
+        assert_eq!(path(&string("notes/a.txt")), Ok(PathBuf::from("notes/a.txt")));
+        assert_eq!(path(&string("$MYTHOS_UNITS_TESTER/a")), Ok(PathBuf::from("/tmp/units/a")));
+        assert_eq!(path(&string("~/notes")), Ok(dirs::get_home().unwrap().join("notes")));
+        assert_eq!(path(&string("$HOME")), Ok(dirs::get_home().unwrap()));
+        assert_eq!(path(&string("$LD/util")), Ok(dirs::expand_path(dirs::MythosDir::LocalData, "util")));
+        assert!(path(&string("$MYTHOS_UNITS_NOT_SET/a")).is_err());
+    }
+    #[test]
+    fn parse_colors() {
+        assert_eq!(color(&string("#ff8800")), Ok(Color { r: 255, g: 136, b: 0, a: 255 }));
+        assert_eq!(color(&string("#f80")), Ok(Color { r: 255, g: 136, b: 0, a: 255 }));
+        assert_eq!(color(&string("#ff880080")), Ok(Color { r: 255, g: 136, b: 0, a: 128 }));
+        assert_eq!(color(&string("#ff880080")).unwrap().to_string(), "#ff880080");
+        assert!(color(&string("ff8800")).is_err());
+        assert!(color(&string("#ff88")).is_ok());
+        assert!(color(&string("#ff88g0")).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+/*!
+ * Crate-owned config values, so the public API does not depend on the toml crate.
+ * Configs are still stored as toml::Table, values are converted when they are returned.
+ *
+ * TOML          | ConfigValue
+ * string          String
+ * integer         Integer
+ * float           Float
+ * boolean         Boolean
+ * datetime        Datetime, in RFC 3339 format
+ * array           Array
+ * table           Table
+ *
+ * With the toml-values feature, ConfigValue converts to and from toml::Value.
+ */
+use std::collections::BTreeMap;
+use std::fmt;
+use toml::Value;
+use super::ValueKind;
+
+pub type ConfigTable = BTreeMap<String, ConfigValue>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConfigValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Datetime(String),
+    Array(Vec<ConfigValue>),
+    Table(ConfigTable),
+}
+
+impl ConfigValue {
+    pub fn kind(&self) -> ValueKind {
+        return match self {
+            ConfigValue::String(_) => ValueKind::String,
+            ConfigValue::Integer(_) => ValueKind::Integer,
+            ConfigValue::Float(_) => ValueKind::Float,
+            ConfigValue::Boolean(_) => ValueKind::Boolean,
+            ConfigValue::Datetime(_) => ValueKind::Datetime,
+            ConfigValue::Array(_) => ValueKind::Array,
+            ConfigValue::Table(_) => ValueKind::Table,
+        };
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        return match self {
+            ConfigValue::String(val) => Some(val),
+            _ => None
+        };
+    }
+    pub fn as_integer(&self) -> Option<i64> {
+        return match self {
+            ConfigValue::Integer(val) => Some(*val),
+            _ => None
+        };
+    }
+    pub fn as_float(&self) -> Option<f64> {
+        return match self {
+            ConfigValue::Float(val) => Some(*val),
+            _ => None
+        };
+    }
+    pub fn as_boolean(&self) -> Option<bool> {
+        return match self {
+            ConfigValue::Boolean(val) => Some(*val),
+            _ => None
+        };
+    }
+    pub fn as_array(&self) -> Option<&Vec<ConfigValue>> {
+        return match self {
+            ConfigValue::Array(val) => Some(val),
+            _ => None
+        };
+    }
+    pub fn as_table(&self) -> Option<&ConfigTable> {
+        return match self {
+            ConfigValue::Table(val) => Some(val),
+            _ => None
+        };
+    }
+}
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        //! Formatted as a TOML value, e.g. strings are quoted.
+        return write!(f, "{}", to_toml(self.clone()));
+    }
+}
+
+pub(crate) fn from_toml(val: &Value) -> ConfigValue {
+    return match val {
+        Value::String(val) => ConfigValue::String(val.to_owned()),
+        Value::Integer(val) => ConfigValue::Integer(*val),
+        Value::Float(val) => ConfigValue::Float(*val),
+        Value::Boolean(val) => ConfigValue::Boolean(*val),
+        Value::Datetime(val) => ConfigValue::Datetime(val.to_string()),
+        Value::Array(val) => ConfigValue::Array(val.iter().map(from_toml).collect()),
+        Value::Table(val) => ConfigValue::Table(from_toml_table(val)),
+    };
+}
+
+pub(crate) fn from_toml_table(table: &toml::Table) -> ConfigTable {
+    return table.iter().map(|(key, val)| (key.to_owned(), from_toml(val))).collect();
+}
+
+pub(crate) fn to_toml(val: ConfigValue) -> Value {
+    return match val {
+        ConfigValue::String(val) => Value::String(val),
+        ConfigValue::Integer(val) => Value::Integer(val),
+        ConfigValue::Float(val) => Value::Float(val),
+        ConfigValue::Boolean(val) => Value::Boolean(val),
+        // Datetimes that don't parse can only come from user code, keep them as strings.
+        ConfigValue::Datetime(val) => match val.parse() {
+            Ok(datetime) => Value::Datetime(datetime),
+            Err(_) => Value::String(val)
+        },
+        ConfigValue::Array(val) => Value::Array(val.into_iter().map(to_toml).collect()),
+        ConfigValue::Table(val) => Value::Table(val.into_iter().map(|(key, val)| (key, to_toml(val))).collect()),
+    };
+}
+
+macro_rules! impl_from {
+    ($variant:ident: $t:ty) => {
+        impl From<$t> for ConfigValue {
+            fn from(val: $t) -> ConfigValue {
+                return ConfigValue::$variant(val.into());
+            }
+        }
+    };
+}
+impl_from!(String: String);
+impl_from!(String: &str);
+impl_from!(Integer: i64);
+impl_from!(Integer: i32);
+impl_from!(Integer: u32);
+impl_from!(Integer: u8);
+impl_from!(Float: f64);
+impl_from!(Float: f32);
+impl_from!(Boolean: bool);
+impl_from!(Table: ConfigTable);
+
+impl<V> From<Vec<V>> for ConfigValue where V: Into<ConfigValue> {
+    fn from(val: Vec<V>) -> ConfigValue {
+        return ConfigValue::Array(val.into_iter().map(|x| x.into()).collect());
+    }
+}
+
+#[cfg(feature = "toml-values")]
+impl From<Value> for ConfigValue {
+    fn from(val: Value) -> ConfigValue {
+        return from_toml(&val);
+    }
+}
+#[cfg(feature = "toml-values")]
+impl From<ConfigValue> for Value {
+    fn from(val: ConfigValue) -> Value {
+        return to_toml(val);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_values() {
+        let table: toml::Table = toml::from_str("a = [1, 2.5, \"x\"]\nb = 1979-05-27T07:32:00Z\n[c]\nd = true").unwrap();
+        let val = from_toml(&Value::Table(table.clone()));
+        let expected = ConfigValue::Table(ConfigTable::from([
+            ("a".into(), ConfigValue::Array(vec![1.into(), 2.5.into(), "x".into()])),
+            ("b".into(), ConfigValue::Datetime("1979-05-27T07:32:00Z".into())),
+            ("c".into(), ConfigValue::Table(ConfigTable::from([("d".into(), true.into())]))),
+        ]));
+        assert_eq!(val, expected);
+        assert_eq!(to_toml(val), Value::Table(table));
+        assert_eq!(ConfigValue::from("x").to_string(), "\"x\"");
+        assert_eq!(ConfigValue::from(vec![1, 2]).kind(), ValueKind::Array);
+    }
+}
@@ -0,0 +1,336 @@
+/*!
+ * Borrowed views into a config, so nested sections can be walked without copying them.
+ * e.g.
+ * let config = MythosConfig::open("arachne")?;
+ * for name in config.view().list_keys() {
+ *     let server = config.view().get_subsection(&name)?;
+ *     let port = server.get_integer("port", 80);
+ * }
+ *
+ * MythosConfig's getters behave exactly like ConfigRef's, they read through config.view().
+ */
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use toml::{Table, Value};
+use super::{coerce, lazy, origin, path, units, value, Coercion, ConfigError, ConfigTable, ConfigValue, MythosConfig, Origin, ValueKind};
+use super::units::Color;
+
+#[derive(Debug, Clone)]
+pub struct ConfigRef<'a> {
+    config: &'a MythosConfig,
+    table: &'a Table,
+    /// Normalized path of table in config, empty for the root.
+    prefix: String,
+}
+
+impl<'a> ConfigRef<'a> {
+    pub(crate) fn new(config: &'a MythosConfig) -> ConfigRef<'a> {
+        return ConfigRef { config, table: &config.table, prefix: String::new() };
+    }
+    fn full_key(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            return path::normalize(key);
+        }
+        return format!("{}.{}", self.prefix, path::normalize(key));
+    }
+    pub(crate) fn get(&self, key: &str) -> Option<&'a Value> {
+        //! key can be a path to a nested value, e.g. "server.ports[1]" or "dict.\"key.with.dots\"".
+        if let Some(val) = path::lookup(self.table, key) {
+            return Some(val);
+        }
+        return lazy::get(&self.config.lazy, &self.full_key(key));
+    }
+    fn get_full(&self, key: &str) -> Option<Cow<'a, Value>> {
+        //! Like get(...), but a table also contains the lazy files inside it, as if they were read eagerly.
+        let val = self.get(key)?;
+        if let (Value::Table(table), false) = (val, self.config.lazy.is_empty()) {
+            let mut table = table.to_owned();
+            if lazy::fill(&mut table, &self.config.lazy, &self.full_key(key)) {
+                return Some(Cow::Owned(Value::Table(table)));
+            }
+        }
+        return Some(Cow::Borrowed(val));
+    }
+    pub fn list_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.table.keys().map(|x| x.to_owned()).collect();
+        keys.extend(lazy::keys_under(&self.config.lazy, &self.prefix));
+        keys.sort();
+        keys.dedup();
+        return keys;
+    }
+    pub fn get_subsection(&self, key: &str) -> Option<ConfigRef<'a>> {
+        //! Borrow the table at key. Unlike MythosConfig::get_subsection(...), nothing is copied.
+        return match self.get(key) {
+            Some(Value::Table(table)) => Some(ConfigRef { config: self.config, table, prefix: self.full_key(key) }),
+            _ => None
+        };
+    }
+    pub fn origin(&self, key: &str) -> Option<&'a Origin> {
+        //! See MythosConfig::origin(...).
+        let key = self.full_key(key);
+        return origin::find(&self.config.origins, &key).or_else(|| lazy::origin(&self.config.lazy, &key));
+    }
+    fn parse<T>(&self, key: &str, parser: fn(&Value) -> Result<T, String>) -> Result<Option<T>, ConfigError> {
+        //! None if key is missing, an error naming key if its value is malformed.
+        return match self.get(key) {
+            Some(val) => parser(val).map(Some).map_err(|message| ConfigError::InvalidValue { key: self.full_key(key), message }),
+            None => Ok(None)
+        };
+    }
+    pub fn to_config(&self) -> MythosConfig {
+        //! Copy this section into its own config.
+        return self.config.subsection(&self.prefix, self.table.to_owned());
+    }
+
+    pub fn get_string(&self, key: &str, default_val: &str) -> String {
+        return self.try_get_string(key).unwrap_or(default_val.to_string());
+    }
+    pub fn try_get_string(&self, key: &str) -> Option<String> {
+        return coerce::string(self.get(key)?, self.config.coercion, &self.full_key(key));
+    }
+    pub fn force_get_string(&self, key: &str) -> Option<String> {
+        return match &self.get(key) {
+            Some(Value::String(val)) => Some(val.to_string()),
+            Some(Value::Float(val)) => Some(format!("{val}")),
+            Some(Value::Integer(val)) => Some(format!("{val}")),
+            Some(Value::Boolean(val)) => Some(format!("{val}")),
+            Some(Value::Datetime(val)) => Some(format!("{val}")),
+            Some(Value::Array(val)) => {
+                let arr: String = val.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" ");
+                Some(arr)
+            },
+            Some(Value::Table(val)) => {
+                let tab: String = val.into_iter().map(|x| format!("{}:{}", x.0, x.1)).collect::<Vec<String>>().join(" ");
+                Some(tab)
+            },
+            None => Some("".into()),
+        }
+    }
+
+    pub fn get_integer(&self, key: &str, default_val: i64) -> i64 {
+        return self.try_get_integer(key).unwrap_or(default_val);
+    }
+    pub fn try_get_integer(&self, key: &str) -> Option<i64> {
+        return coerce::integer(self.get(key)?, self.config.coercion, &self.full_key(key));
+    }
+
+    pub fn get_float(&self, key: &str, default_val: f64) -> f64 {
+        return self.try_get_float(key).unwrap_or(default_val);
+    }
+    pub fn try_get_float(&self, key: &str) -> Option<f64> {
+        return coerce::float(self.get(key)?, self.config.coercion, &self.full_key(key));
+    }
+    pub fn get_boolean(&self, key: &str, default_val: bool) -> bool {
+        return self.try_get_boolean(key).unwrap_or(default_val);
+    }
+    pub fn try_get_boolean(&self, key: &str) -> Option<bool> {
+        return coerce::boolean(self.get(key)?, self.config.coercion, &self.full_key(key));
+    }
+
+    fn parse_datetime_strings(&self) -> bool {
+        return self.config.parse_datetime_strings || self.config.coercion == Coercion::Lenient;
+    }
+    pub fn get_datetime(&self, key: &str, default_val: &str) -> String{
+        return self.try_get_datetime(key).unwrap_or(default_val.to_string());
+    }
+    pub fn try_get_datetime(&self, key: &str) -> Option<String> {
+        return coerce::datetime_string(self.get(key)?, self.parse_datetime_strings(), &self.full_key(key));
+    }
+    pub fn get_date(&self, key: &str, default_val: NaiveDate) -> NaiveDate {
+        return self.try_get_date(key).unwrap_or(default_val);
+    }
+    pub fn try_get_date(&self, key: &str) -> Option<NaiveDate> {
+        //! A local date, e.g. 1979-05-27.
+        return coerce::date(self.get(key)?, self.parse_datetime_strings(), &self.full_key(key));
+    }
+    pub fn get_time(&self, key: &str, default_val: NaiveTime) -> NaiveTime {
+        return self.try_get_time(key).unwrap_or(default_val);
+    }
+    pub fn try_get_time(&self, key: &str) -> Option<NaiveTime> {
+        //! A local time, e.g. 07:32:00.
+        return coerce::time(self.get(key)?, self.parse_datetime_strings(), &self.full_key(key));
+    }
+    pub fn get_naive_datetime(&self, key: &str, default_val: NaiveDateTime) -> NaiveDateTime {
+        return self.try_get_naive_datetime(key).unwrap_or(default_val);
+    }
+    pub fn try_get_naive_datetime(&self, key: &str) -> Option<NaiveDateTime> {
+        //! A local datetime, e.g. 1979-05-27T07:32:00.
+        return coerce::naive_datetime(self.get(key)?, self.parse_datetime_strings(), &self.full_key(key));
+    }
+    pub fn get_datetime_tz(&self, key: &str, default_val: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        return self.try_get_datetime_tz(key).unwrap_or(default_val);
+    }
+    pub fn try_get_datetime_tz(&self, key: &str) -> Option<DateTime<FixedOffset>> {
+        //! An offset datetime, e.g. 1979-05-27T07:32:00Z or 1979-05-27T00:32:00-07:00.
+        return coerce::datetime_tz(self.get(key)?, self.parse_datetime_strings(), &self.full_key(key));
+    }
+    pub fn get_array(&self, key: &str, default_val: Vec<ConfigValue>) -> Vec<ConfigValue> {
+        return self.try_get_array(key).unwrap_or(default_val);
+    }
+    pub fn try_get_array(&self, key: &str) -> Option<Vec<ConfigValue>> {
+        return match self.get(key) {
+            Some(Value::Array(val)) => Some(val.iter().map(value::from_toml).collect()),
+            _ => None
+        };
+    }
+    pub fn get_typed_array<'de, T>(&self, key: &str) -> Vec<T> where T: serde::Deserialize<'de> {
+        //! Elements that can't be converted into T, even with the coercion policy, are skipped.
+        let array = match self.get(key) {
+            Some(Value::Array(array)) => array,
+            _ => return Vec::new()
+        };
+        return array.iter().enumerate()
+            .filter_map(|(i, val)| coerce::element(val, self.config.coercion, &format!("{}[{i}]", self.full_key(key))).ok())
+            .collect();
+    }
+    pub fn try_get_typed_array<T>(&self, key: &str) -> Result<Vec<T>, ConfigError> where T: serde::de::DeserializeOwned {
+        //! Unlike get_typed_array(...), fails if key is missing or any element can't be converted.
+        //! The error names the element, e.g. "ports[1]", and its value.
+        let array = match self.get(key) {
+            Some(Value::Array(array)) => array,
+            Some(val) => return Err(ConfigError::InvalidValue {
+                key: self.full_key(key),
+                message: format!("expected an array, found {} {val}", ValueKind::of_toml(val)),
+            }),
+            None => return Err(ConfigError::MissingKey(self.full_key(key)))
+        };
+        return array.iter().enumerate()
+            .map(|(i, val)| {
+                let element = format!("{}[{i}]", self.full_key(key));
+                coerce::element(val, self.config.coercion, &element).map_err(|err| {
+                    let inner = err.path().to_string();
+                    ConfigError::Deserialize {
+                        key: if inner == "." { element } else { format!("{element}.{inner}") },
+                        message: format!("{} (value: {val})", err.into_inner().message()),
+                    }
+                })
+            })
+            .collect();
+    }
+    pub fn get_table_array(&self, key: &str) -> Vec<MythosConfig> {
+        //! Each table of an array of tables, e.g. [[servers]]. Empty if key is missing or not an array of tables.
+        return self.try_get_table_array(key).unwrap_or_default();
+    }
+    pub fn try_get_table_array(&self, key: &str) -> Option<Vec<MythosConfig>> {
+        //! None if key is missing, not an array, or has an element that is not a table.
+        let array = match self.get(key) {
+            Some(Value::Array(array)) => array,
+            _ => return None
+        };
+        return array.iter().enumerate()
+            .map(|(i, val)| {
+                let element = format!("{}[{i}]", self.full_key(key));
+                let mut config = self.config.subsection(&element, val.as_table()?.to_owned());
+                // Arrays are leaves, so every element shares the array's origin.
+                if let Some(origin) = self.origin(key) {
+                    config.origins = origin::leaves(&config.table).into_iter().map(|x| (x, origin.to_owned())).collect();
+                }
+                Some(config)
+            })
+            .collect();
+    }
+    pub fn get_table(&self, key: &str, default_val: ConfigTable) -> ConfigTable {
+        return self.try_get_table(key).unwrap_or(default_val);
+    }
+    pub fn try_get_table(&self, key: &str) -> Option<ConfigTable> {
+        return match self.get_full(key)?.as_ref() {
+            Value::Table(val) => Some(value::from_toml_table(val)),
+            _ => None
+        };
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        return self.get(key).is_some();
+    }
+    pub fn type_of(&self, key: &str) -> Option<ValueKind> {
+        return self.get(key).map(ValueKind::of_toml);
+    }
+    pub fn entries(&self) -> impl Iterator<Item = (String, ConfigValue)> + 'a {
+        //! Every top-level key of this section and its value, sorted by key.
+        let view = self.clone();
+        return self.list_keys().into_iter()
+            .filter_map(move |key| {
+                let val = value::from_toml(view.get_full(&path::join(&[&key]))?.as_ref());
+                Some((key, val))
+            });
+    }
+
+    /*
+     * The toml crate's types, for utils that have not moved to ConfigValue yet.
+     * Unlike the methods above, the _ref variants borrow instead of copying.
+     */
+    #[cfg(feature = "toml-values")]
+    pub fn get_toml_array(&self, key: &str, default_val: Vec<Value>) -> Vec<Value> {
+        return self.try_get_toml_array(key).unwrap_or(default_val);
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_array(&self, key: &str) -> Option<Vec<Value>> {
+        return self.try_get_toml_array_ref(key).map(|x| x.to_owned());
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_array_ref(&self, key: &str) -> Option<&'a [Value]> {
+        return match self.get(key) {
+            Some(Value::Array(val)) => Some(val),
+            _ => None
+        };
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn get_toml_table(&self, key: &str, default_val: Table) -> Table {
+        return self.try_get_toml_table(key).unwrap_or(default_val);
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_table(&self, key: &str) -> Option<Table> {
+        return match self.get_full(key)?.into_owned() {
+            Value::Table(val) => Some(val),
+            _ => None
+        };
+    }
+    #[cfg(feature = "toml-values")]
+    pub fn try_get_toml_table_ref(&self, key: &str) -> Option<&'a Table> {
+        //! Lazy files inside the table are not read into it, use try_get_toml_table(...) for those.
+        return match self.get(key) {
+            Some(Value::Table(val)) => Some(val),
+            _ => None
+        };
+    }
+
+    /*
+     * Human-friendly values, see units.rs for the accepted formats.
+     * default_val is only used if key is missing. Malformed values are errors.
+     */
+    pub fn get_duration(&self, key: &str, default_val: Duration) -> Result<Duration, ConfigError> {
+        return Ok(self.try_get_duration(key)?.unwrap_or(default_val));
+    }
+    pub fn try_get_duration(&self, key: &str) -> Result<Option<Duration>, ConfigError> {
+        return self.parse(key, units::duration);
+    }
+    pub fn get_size(&self, key: &str, default_val: u64) -> Result<u64, ConfigError> {
+        //! Size in bytes.
+        return Ok(self.try_get_size(key)?.unwrap_or(default_val));
+    }
+    pub fn try_get_size(&self, key: &str) -> Result<Option<u64>, ConfigError> {
+        return self.parse(key, units::size);
+    }
+    pub fn get_path(&self, key: &str, default_val: &Path) -> Result<PathBuf, ConfigError> {
+        //! Expands ~, $HOME, mythos dirs and environment variables at the start of the path. default_val is not expanded.
+        return Ok(self.try_get_path(key)?.unwrap_or(default_val.to_owned()));
+    }
+    pub fn try_get_path(&self, key: &str) -> Result<Option<PathBuf>, ConfigError> {
+        return self.parse(key, units::path);
+    }
+    pub fn get_color(&self, key: &str, default_val: Color) -> Result<Color, ConfigError> {
+        return Ok(self.try_get_color(key)?.unwrap_or(default_val));
+    }
+    pub fn try_get_color(&self, key: &str) -> Result<Option<Color>, ConfigError> {
+        return self.parse(key, units::color);
+    }
+}
+
+impl<'a> IntoIterator for ConfigRef<'a> {
+    type Item = (String, ConfigValue);
+    type IntoIter = std::vec::IntoIter<(String, ConfigValue)>;
+    fn into_iter(self) -> Self::IntoIter {
+        return self.entries().collect::<Vec<_>>().into_iter();
+    }
+}
@@ -0,0 +1,235 @@
+/*!
+ * Reload configs when their files change.
+ * e.g.
+ * let _watcher = conf::watch("arachne", |config| match config {
+ *     Ok(config) => apply(config),
+ *     Err(err) => printerror!("{err}"),
+ * })?;
+ *
+ * The watcher stops when it is dropped.
+ */
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use crate::dirs;
+use super::{ConfigError, ConfigOptions, MythosConfig};
+
+/// Editors often save by writing a temp file, then renaming it over the original.
+/// Wait until no events have arrived for this long before reloading.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub type WatchResult = Result<MythosConfig, ConfigError>;
+
+/// Stops watching when dropped.
+pub struct ConfigWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+pub fn watch<F>(util_name: &str, callback: F) -> Result<ConfigWatcher, ConfigError>
+where F: FnMut(WatchResult) + Send + 'static {
+    //! Call callback with the reloaded config (or the reason it couldn't be loaded) whenever it changes.
+    //! The config is reloaded using MythosConfig::load(...).
+    return watch_with(util_name, &ConfigOptions::default(), callback);
+}
+
+pub fn watch_with<F>(util_name: &str, options: &ConfigOptions, mut callback: F) -> Result<ConfigWatcher, ConfigError>
+where F: FnMut(WatchResult) + Send + 'static {
+    let mut watches = Watches::new(util_name)?;
+    let util_name = util_name.to_string();
+    let options = options.clone();
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+
+    let handle = std::thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        let mut last_event: Option<Instant> = None;
+        while !thread_stop.load(Ordering::Relaxed) {
+            if watches.has_changes(&mut buffer) {
+                last_event = Some(Instant::now());
+            }
+            if last_event.is_some_and(|x| x.elapsed() >= DEBOUNCE) {
+                last_event = None;
+                callback(MythosConfig::load_with(&util_name, &options));
+                // Directories may have been created or removed.
+                watches.refresh();
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+
+    return Ok(ConfigWatcher { stop, handle: Some(handle) });
+}
+
+pub fn watch_channel(util_name: &str) -> Result<(ConfigWatcher, Receiver<WatchResult>), ConfigError> {
+    //! Same as watch(...), but reloaded configs are sent through a channel.
+    let (sender, receiver) = channel();
+    let watcher = watch(util_name, move |config| {
+        let _ = sender.send(config);
+    })?;
+    return Ok((watcher, receiver));
+}
+
+struct Watches {
+    util_name: String,
+    inotify: Inotify,
+    /// Watched dir -> name of the only entry that matters, or None if every entry matters.
+    filters: HashMap<WatchDescriptor, Option<OsString>>,
+    /// Closest existing ancestor of a config dir that doesn't exist yet -> name of its missing child.
+    ancestors: HashMap<WatchDescriptor, OsString>,
+}
+
+impl Watches {
+    fn new(util_name: &str) -> Result<Watches, ConfigError> {
+        let inotify = Inotify::init().map_err(|err| ConfigError::io(Path::new(util_name), err))?;
+        let mut watches = Watches { util_name: util_name.to_string(), inotify, filters: HashMap::new(), ancestors: HashMap::new() };
+        watches.refresh();
+        return Ok(watches);
+    }
+    fn refresh(&mut self) {
+        //! (Re)watch every place the config could be read from.
+        let mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::CLOSE_WRITE
+            | WatchMask::MOVED_TO | WatchMask::MOVED_FROM | WatchMask::DELETE_SELF;
+        let old: Vec<WatchDescriptor> = self.filters.drain().map(|x| x.0).chain(self.ancestors.drain().map(|x| x.0)).collect();
+        for wd in old {
+            let _ = self.inotify.watches().remove(wd);
+        }
+
+        for dir in [dirs::MythosDir::LocalConfig, dirs::MythosDir::Config] {
+            let path = dirs::expand_path(dir, &self.util_name);
+            // Catches the file being created, replaced or removed.
+            if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+                if let Ok(wd) = self.inotify.watches().add(parent, mask) {
+                    self.filters.insert(wd, Some(name.to_owned()));
+                } else if let Some((ancestor, missing)) = existing_ancestor(parent) {
+                    // Catches e.g. ~/.config/mythos being created after the watcher started.
+                    if let Ok(wd) = self.inotify.watches().add(ancestor, mask) {
+                        self.ancestors.insert(wd, missing);
+                    }
+                }
+            }
+            if path.is_dir() {
+                for dir in sub_dirs(&path) {
+                    if let Ok(wd) = self.inotify.watches().add(&dir, mask) {
+                        self.filters.insert(wd, None);
+                    }
+                }
+            }
+        }
+    }
+    fn has_changes(&mut self, buffer: &mut [u8]) -> bool {
+        //! Read pending events. Returns true if any of them affect the config.
+        let events = match self.inotify.read_events(buffer) {
+            Ok(events) => events,
+            Err(_) => return false
+        };
+        let mut changed = false;
+        let mut created = false;
+        for event in events {
+            if let (Some(missing), Some(name)) = (self.ancestors.get(&event.wd), event.name) {
+                created |= name == missing;
+                continue;
+            }
+            let name = match event.name {
+                Some(name) => name,
+                None => {
+                    // A watched directory itself changed.
+                    changed = true;
+                    continue;
+                }
+            };
+            changed |= match self.filters.get(&event.wd) {
+                Some(Some(filter)) => matches_filter(name, filter),
+                Some(None) => !is_temp_file(name),
+                None => false
+            };
+        }
+        if created {
+            // The config may have been written before its new dir was watched.
+            self.refresh();
+            changed |= super::try_get_file(&self.util_name, true).is_ok();
+        }
+        return changed;
+    }
+}
+
+fn matches_filter(name: &OsStr, filter: &OsStr) -> bool {
+    //! "arachne" matches "arachne" and "arachne.ext", but not editor backups like "arachne.toml~".
+    if is_temp_file(name) {
+        return false;
+    }
+    let (name, filter) = (name.to_string_lossy(), filter.to_string_lossy());
+    return name == filter || name.strip_prefix(filter.as_ref()).is_some_and(|x| x.starts_with('.'));
+}
+
+fn is_temp_file(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    return name.starts_with('.') || name.ends_with('~') || name.ends_with(".swp") || name.ends_with(".swx");
+}
+
+fn existing_ancestor(path: &Path) -> Option<(&Path, OsString)> {
+    //! The closest ancestor of path that exists, and the name of its child that leads to path.
+    let mut child = path;
+    for ancestor in path.ancestors().skip(1) {
+        if ancestor.is_dir() {
+            return Some((ancestor, child.file_name()?.to_owned()));
+        }
+        child = ancestor;
+    }
+    return None;
+}
+
+fn sub_dirs(path: &Path) -> Vec<PathBuf> {
+    //! path and every directory below it. Symlinked directories are not followed.
+    let mut dirs = vec![path.to_owned()];
+    let mut i = 0;
+    while i < dirs.len() {
+        if let Ok(entries) = dirs[i].read_dir() {
+            for entry in entries.flatten() {
+                if entry.file_type().is_ok_and(|x| x.is_dir()) {
+                    dirs.push(entry.path());
+                }
+            }
+        }
+        i += 1;
+    }
+    return dirs;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_names() {
+        assert!(matches_filter(OsStr::new("arachne"), OsStr::new("arachne")));
+        assert!(matches_filter(OsStr::new("arachne.toml"), OsStr::new("arachne")));
+        assert!(!matches_filter(OsStr::new("arachne2.toml"), OsStr::new("arachne")));
+        assert!(!matches_filter(OsStr::new("arachne.toml~"), OsStr::new("arachne")));
+        assert!(!matches_filter(OsStr::new(".arachne.toml.swp"), OsStr::new("arachne")));
+    }
+    #[test]
+    fn find_existing_ancestor() {
+        let root = std::env::temp_dir();
+        let path = root.join("mythos-core-missing/a/b");
+        let (ancestor, missing) = existing_ancestor(&path).unwrap();
+        assert_eq!(ancestor, root);
+        assert_eq!(missing, "mythos-core-missing");
+    }
+}
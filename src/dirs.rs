@@ -64,7 +64,7 @@ pub fn make_dir(dir_name: MythosDir, util_name: &str) -> Result<PathBuf, std::io
     // create_dir_all fails if dir already exists or user doesn't have permissions
     // This function should only throw an error for the latter
     if !path.exists() {
-        let _ = fs::create_dir_all(path.clone())?;
+        fs::create_dir_all(path.clone())?;
     }
 
     return Ok(path);
@@ -95,7 +95,7 @@ pub fn expand_path(dir_name: MythosDir, util_name: &str) -> PathBuf {
     let path = Path::new(&path_name);
 
     // If only asking for core, return
-    if util_name.to_lowercase() == "core" || util_name == "" {
+    if util_name.to_lowercase() == "core" || util_name.is_empty() {
         return path.to_owned();
     }
 
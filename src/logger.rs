@@ -1,5 +1,6 @@
 use std::{fs::{File, OpenOptions}, io::{BufWriter, Error, Write}, path::PathBuf};
 use std::cell::RefCell;
+use std::sync::atomic::{AtomicU8, Ordering};
 use crate::dirs::{self, MythosDir};
 
 
@@ -60,7 +61,12 @@ macro_rules! fatalmsg{
 }
 
 // Singlethreaded access to global logger.
-thread_local!(static LOGGER: RefCell<Logger> = RefCell::new(Logger::new("MYTHOS").unwrap()));
+// If the log dir can't be created, messages are dropped instead of panicking.
+thread_local!(static LOGGER: RefCell<Logger> = RefCell::new(Logger::new("MYTHOS").unwrap_or(Logger::disabled("MYTHOS"))));
+
+/// Messages below this level are not written. Shared by every thread.
+static LEVEL: AtomicU8 = AtomicU8::new(UNSET);
+const UNSET: u8 = u8::MAX;
 
 /// Change the id assigned to the logger. Default is MYTHOS.
 pub fn set_id(id: &str) -> Result<(), Error> {
@@ -69,27 +75,67 @@ pub fn set_id(id: &str) -> Result<(), Error> {
     return Ok(());
 }
 
+/// Only write messages at or above level. Overrides $MYTHOS_LOG_LEVEL.
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+pub fn level() -> LogLevel {
+    //! Set by set_level(...), else $MYTHOS_LOG_LEVEL (debug, info, warn, error or fatal), else Info.
+    let level = match LEVEL.load(Ordering::Relaxed) {
+        UNSET => {
+            let level = std::env::var("MYTHOS_LOG_LEVEL").ok().and_then(|x| LogLevel::parse(&x)).unwrap_or(LogLevel::Info);
+            LEVEL.store(level as u8, Ordering::Relaxed);
+            return level;
+        },
+        level => level
+    };
+    return LogLevel::ALL.into_iter().find(|x| *x as u8 == level).unwrap_or(LogLevel::Info);
+}
+
+pub fn debug(msg: &str) -> String {
+    return log(msg, LogLevel::Debug);
+}
 pub fn info(msg: &str) -> String {
-    LOGGER.with(|logger| logger.borrow_mut().write(msg, LogLevel::Info))
+    return log(msg, LogLevel::Info);
 }
 pub fn warn(msg: &str) -> String {
-    LOGGER.with(|logger| logger.borrow_mut().write(msg, LogLevel::Warn))
+    return log(msg, LogLevel::Warn);
 }
 pub fn error(msg: &str) -> String {
-    LOGGER.with(|logger| logger.borrow_mut().write(msg, LogLevel::Error))
+    return log(msg, LogLevel::Error);
 }
 pub fn fatal(msg: &str) -> String {
-    LOGGER.with(|logger| logger.borrow_mut().write(msg, LogLevel::Fatal))
+    return log(msg, LogLevel::Fatal);
 }
+fn log(msg: &str, level: LogLevel) -> String {
+    //! Returns the formatted entry, even if it was filtered out.
+    if level < self::level() {
+        return Logger::format(msg, level);
+    }
+    return LOGGER.try_with(|logger| logger.borrow_mut().write(msg, level))
+        .unwrap_or_else(|_| Logger::format(msg, level));
+}
+
+
+/// Ordered from least to most severe, see set_level(...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum LogLevel { Debug, Info, Warn, Error, Fatal }
 
+impl LogLevel {
+    const ALL: [LogLevel; 5] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error, LogLevel::Fatal];
 
-#[derive(Debug)]
-pub enum LogLevel { Info, Warn, Error, Fatal }
+    fn parse(name: &str) -> Option<LogLevel> {
+        return LogLevel::ALL.into_iter().find(|x| format!("{x:?}").eq_ignore_ascii_case(name.trim()));
+    }
+}
 
 /// Writes to log file.
 struct Logger {
+    #[allow(dead_code)]
     id: String,
-    writer: BufWriter<File>,
+    /// None if the log file could not be opened.
+    writer: Option<BufWriter<File>>,
 }
 
 impl Logger {
@@ -110,14 +156,22 @@ impl Logger {
 
         return Ok(Logger {
             id: id.to_string(),
-            writer: BufWriter::new(file),
+            writer: Some(BufWriter::new(file)),
         });
     }
-    pub fn write(&mut self, msg: &str, level: LogLevel) -> String {
+    fn disabled(id: &str) -> Logger {
+        return Logger { id: id.to_string(), writer: None };
+    }
+    fn format(msg: &str, level: LogLevel) -> String {
         let timestamp = chrono::Local::now();
-        let msg = format!("{timestamp} {level:#?}: {msg}\n");
-        let _ = self.writer.write(&msg.clone().into_bytes());
-        return msg.to_string();
+        return format!("{timestamp} {level:#?}: {msg}\n");
+    }
+    pub fn write(&mut self, msg: &str, level: LogLevel) -> String {
+        let msg = Logger::format(msg, level);
+        if let Some(writer) = self.writer.as_mut() {
+            let _ = writer.write(msg.as_bytes());
+        }
+        return msg;
     }
 }
 
@@ -131,6 +185,14 @@ mod test {
         super::info("Test entry");
     }
     #[test]
+    fn test_levels() {
+        assert_eq!(super::LogLevel::parse("DEBUG"), Some(super::LogLevel::Debug));
+        assert_eq!(super::LogLevel::parse("verbose"), None);
+        assert!(super::LogLevel::Debug < super::LogLevel::Info);
+        // Filtered entries are still formatted.
+        assert!(super::debug("Not written").ends_with("Debug: Not written\n"));
+    }
+    #[test]
     fn test_print_info() {
         let val = true;
         printinfo!(val, "Do print {}.", "this");
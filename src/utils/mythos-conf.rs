@@ -1,31 +1,123 @@
-use mythos_core::conf::MythosConfig;
+use mythos_core::conf::{self, ConfigOptions, MythosConfig};
+use mythos_core::{dirs, printfatal};
 use std::env;
+use std::path::Path;
 
 fn main() {
-    let mut args = env::args().skip(1);
+    let mut args = env::args().skip(1).peekable();
+    let mut explain = false;
+    let mut options = ConfigOptions::default();
+    while let Some(flag) = args.next_if(|x| x.starts_with("--")) {
+        match flag.as_str() {
+            "--explain" => explain = true,
+            "--profile" => options.profile = args.next(),
+            _ => printfatal!("Unknown flag '{flag}'")
+        }
+    }
+    if args.next_if(|x| x == "diff").is_some() {
+        let util_name = args.next().unwrap_or_default();
+        match diff_args(args) {
+            Ok((against, json)) => print!("{}", diff_configs(&util_name, &against, json, &options)),
+            Err(err) => printfatal!("{err}")
+        }
+        return;
+    }
     if let Some(util_name) = args.next() {
-        println!("{data}", data = get_value(&util_name, args.map(|x| x).collect()));
+        if explain {
+            print!("{}", explain_value(&util_name, args.collect(), &options));
+        } else {
+            println!("{data}", data = get_value(&util_name, args.collect(), &options));
+        }
     }
 }
 
-fn get_value(util_name: &str, mut keys: Vec<String>) -> String {
-    let mut conf = match MythosConfig::open_file(&util_name) {
+fn get_value(util_name: &str, keys: Vec<String>, options: &ConfigOptions) -> String {
+    //! Each key can be a path, e.g. `mythos-conf util server ports[1]` == `mythos-conf util server.ports[1]`
+    let conf = match MythosConfig::open_file_with(util_name, options) {
         Some(conf) => conf,
         None => return "".into()
     };
-    while keys.len() > 1 {
-        conf = match conf.get_subsection(&keys.remove(0)) {
-            Some(conf) => conf,
-            None => return "".into()
-        };
-    }
 
-    return match conf.force_get_string(&keys.remove(0)) {
+    return match conf.force_get_string(&key_path(&conf, &keys)) {
         Some(val) => val,
         None => "".into()
     }
 }
 
+fn key_path(conf: &MythosConfig, keys: &[String]) -> String {
+    //! Each argument is a key of the previous one, or a path if no key is named like it.
+    //! So `mythos-conf util server host.name` reads server."host.name" when it exists, else server.host.name.
+    let mut path = String::new();
+    for key in keys {
+        let sep = if path.is_empty() { "" } else { "." };
+        let quoted = format!("{path}{sep}{}", conf::join_keys(&[key]));
+        path = if conf.contains_key(&quoted) { quoted } else { format!("{path}{sep}{key}") };
+    }
+    return path;
+}
+
+fn explain_value(util_name: &str, keys: Vec<String>, options: &ConfigOptions) -> String {
+    //! `mythos-conf --explain util key`: the value, where it came from and every path that was searched.
+    let mut output = String::new();
+    match MythosConfig::load_file_with(util_name, options) {
+        Ok(conf) => {
+            let key = key_path(&conf, &keys);
+            match (conf.force_get_string(&key), conf.origin(&key)) {
+                (Some(val), Some(origin)) => output.push_str(&format!("{key} = {val}\nfrom {origin}\n")),
+                (Some(val), None) => output.push_str(&format!("{key} = {val}\n")),
+                (None, _) => output.push_str(&format!("{key} is not set\n")),
+            }
+        },
+        Err(err) => output.push_str(&format!("{err}\n")),
+    }
+    if let Some(profile) = &options.profile {
+        output.push_str(&format!("profile: {profile}\n"));
+    }
+
+    output.push_str("searched:\n");
+    for path in conf::probed_paths(util_name, false) {
+        let status = if path.is_file() { "found" } else { "missing" };
+        output.push_str(&format!("  {status:<7} {}\n", path.display()));
+    }
+    return output;
+}
+
+fn diff_args(mut args: impl Iterator<Item = String>) -> Result<(String, bool), String> {
+    //! The flags after `mythos-conf diff util`: where to compare against, and whether to print JSON.
+    let mut against = "global".to_string();
+    let mut json = false;
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--against" => against = args.next().ok_or("--against needs defaults, global or a path")?,
+            "--json" => json = true,
+            _ => return Err(format!("Unknown flag '{flag}'"))
+        }
+    }
+    return Ok((against, json));
+}
+
+fn diff_configs(util_name: &str, against: &str, json: bool, options: &ConfigOptions) -> String {
+    //! `mythos-conf diff util [--against defaults|global|PATH] [--json]`: how util's config differs from another.
+    //! global is $MYTHOS_CONFIG_DIR/util, defaults is the file written by MythosConfig::install_defaults(...).
+    //! Both sides are loaded with the same options.
+    let other = match against {
+        "global" => dirs::expand_path(dirs::MythosDir::Config, util_name),
+        "defaults" => conf::defaults_path(util_name),
+        path => Path::new(path).to_path_buf(),
+    };
+    let diff = match (MythosConfig::load_path_with(&other, options), MythosConfig::load_file_with(util_name, options)) {
+        (Ok(other), Ok(conf)) => other.diff(&conf),
+        (Err(err), _) | (_, Err(err)) => return format!("{err}\n"),
+    };
+    if json {
+        #[cfg(feature = "json")]
+        return format!("{}\n", diff.to_json());
+        #[cfg(not(feature = "json"))]
+        return "--json requires mythos-core's json feature\n".into();
+    }
+    return diff.to_string();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,6 +129,58 @@ mod tests {
     #[test]
     fn test_get_number() {
         setup();
-        assert_eq!(get_value("config_tester", vec!["array".into()]), "0 1");
+        assert_eq!(get_value("config_tester", vec!["array".into()], &ConfigOptions::default()), "0 1");
+        assert_eq!(get_value("config_tester", vec!["table".into(), "int2".into()], &ConfigOptions::default()), "2");
+        assert_eq!(get_value("config_tester", vec!["array[1]".into()], &ConfigOptions::default()), "1");
+        assert_eq!(get_value("config_tester", vec!["table".into(), "host.name".into()], &ConfigOptions::default()), "localhost");
+        assert_eq!(get_value("config_tester", vec!["table.int1".into()], &ConfigOptions::default()), "1");
+    }
+    #[test]
+    fn test_explain() {
+        setup();
+        let output = explain_value("config_tester", vec!["table".into(), "int2".into()], &ConfigOptions::default());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "table.int2 = 2");
+        assert_eq!(lines[1], "from tests/lconfig/config_tester.toml:19");
+        assert_eq!(lines[3], "  missing tests/lconfig/config_tester");
+        assert_eq!(lines.last(), Some(&"  found   tests/lconfig/config_tester.toml"));
+
+        let output = explain_value("config_tester", vec!["table".into(), "host.name".into()], &ConfigOptions::default());
+        assert!(output.starts_with("table.\"host.name\" = localhost\nfrom tests/lconfig/config_tester.toml:20\n"));
+    }
+    #[test]
+    fn test_diff() {
+        setup();
+        let output = diff_configs("layered", "global", false, &ConfigOptions::default());
+        assert!(output.contains("- keep = 1\n"));
+        assert!(output.contains("- name = \"global\"\n+ name = \"local\"\n"));
+        assert_eq!(diff_configs("layered", "tests/lconfig/layered", false, &ConfigOptions::default()), "");
+        assert!(diff_configs("layered", "tests/missing", false, &ConfigOptions::default()).starts_with("Could not find"));
+
+        let options = ConfigOptions { interpolate: true, ..Default::default() };
+        assert_eq!(diff_configs("interpolate_tester", "tests/config/interpolate_tester", false, &options), "");
+
+        let args = |x: &[&str]| diff_args(x.iter().map(|x| x.to_string()));
+        assert_eq!(args(&[]), Ok(("global".into(), false)));
+        assert_eq!(args(&["--against", "defaults", "--json"]), Ok(("defaults".into(), true)));
+        assert!(args(&["--against"]).is_err());
+        assert_eq!(args(&["--jsn"]), Err("Unknown flag '--jsn'".into()));
+    }
+    #[test]
+    fn test_diff_defaults() {
+        setup();
+        let dir = std::env::temp_dir().join(format!("mythos-conf-defaults-{}", std::process::id()));
+        std::env::set_var("MYTHOS_DATA_DIR", &dir);
+        MythosConfig::install_defaults("layered", "name = \"local\"\nlist = [2, 3]\nwidth = 80\n").unwrap();
+        let output = diff_configs("layered", "defaults", false, &ConfigOptions::default());
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(output.lines().collect::<Vec<_>>(), vec!["+ section.b = 20", "- width = 80"]);
+    }
+    #[test]
+    fn test_profile() {
+        setup();
+        let options = ConfigOptions { profile: Some("work".into()), ..Default::default() };
+        assert_eq!(get_value("profile_tester", vec!["editor".into()], &options), "code");
+        assert_eq!(get_value("profile_tester", vec!["editor".into()], &ConfigOptions::default()), "vim");
     }
 }